@@ -0,0 +1,88 @@
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+use crate::expr::ExpLiteralValue;
+
+pub enum Offer {
+    Accepted,
+    Queued(u64),
+    Full(Box<ExpLiteralValue>),
+}
+
+#[derive(Debug)]
+struct ChannelState {
+    capacity: usize,
+    queue: VecDeque<ExpLiteralValue>,
+    sent: u64,
+    received: u64,
+}
+
+// A capacity of zero makes an unbuffered channel: the sender's value is
+// queued, but the sender stays blocked until a receiver has taken it.
+#[derive(Debug, Clone)]
+pub struct Channel {
+    state: Rc<RefCell<ChannelState>>,
+}
+
+impl PartialEq for Channel {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl PartialOrd for Channel {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        if self == other {
+            Some(std::cmp::Ordering::Equal)
+        } else {
+            None
+        }
+    }
+}
+
+impl Channel {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(ChannelState {
+                capacity,
+                queue: VecDeque::new(),
+                sent: 0,
+                received: 0,
+            })),
+        }
+    }
+
+    pub fn offer(&self, value: ExpLiteralValue) -> Offer {
+        let mut state = self.state.borrow_mut();
+
+        if state.capacity == 0 {
+            let ticket = state.sent;
+            state.sent += 1;
+            state.queue.push_back(value);
+            Offer::Queued(ticket)
+        } else if state.queue.len() < state.capacity {
+            state.sent += 1;
+            state.queue.push_back(value);
+            Offer::Accepted
+        } else {
+            Offer::Full(Box::new(value))
+        }
+    }
+
+    pub fn is_taken(&self, ticket: u64) -> bool {
+        self.state.borrow().received > ticket
+    }
+
+    pub fn take(&self) -> Option<ExpLiteralValue> {
+        let mut state = self.state.borrow_mut();
+
+        let value = state.queue.pop_front()?;
+        state.received += 1;
+        Some(value)
+    }
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "<kanaal {}>", self.state.borrow().capacity)
+    }
+}
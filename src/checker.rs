@@ -0,0 +1,554 @@
+use std::collections::{HashMap, HashSet};
+
+use anyhow::Result;
+
+use crate::{
+    error::{Error, Errors},
+    expr::{ExpLiteralValue, Expr},
+    lexer::{Token, TokenType},
+    message::{Message, Operation},
+    pattern::{Pattern, PatternItem},
+    statement::Stmt,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Number,
+    String,
+    Bool,
+    Nil,
+    List,
+    Tuple,
+    Set,
+    Function {
+        params: Vec<Type>,
+        returns: Box<Type>,
+    },
+    Enum(String),
+    Unknown,
+}
+
+impl Type {
+    // `Wax` and anything the checker could not work out are compatible
+    // with every type; that is what keeps unannotated code unchecked.
+    fn accepts(&self, other: &Type) -> bool {
+        match (self, other) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (Type::Function { .. }, Type::Function { .. }) => true,
+            (expected, actual) => expected == actual,
+        }
+    }
+
+    fn is_known(&self) -> bool {
+        *self != Type::Unknown
+    }
+
+    fn of_value(value: &ExpLiteralValue) -> Type {
+        match value {
+            ExpLiteralValue::Number(_) => Type::Number,
+            ExpLiteralValue::StringValue(_) => Type::String,
+            ExpLiteralValue::True | ExpLiteralValue::False => Type::Bool,
+            ExpLiteralValue::Nil => Type::Nil,
+            _ => Type::Unknown,
+        }
+    }
+}
+
+impl std::fmt::Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Type::Number => write!(f, "Tiro"),
+            Type::String => write!(f, "Qoraal"),
+            Type::Bool => write!(f, "Xaqiiq"),
+            Type::Nil => write!(f, "Waxba"),
+            Type::List => write!(f, "Liis"),
+            Type::Tuple => write!(f, "Koox"),
+            Type::Set => write!(f, "Urur"),
+            Type::Function { .. } => write!(f, "Hawl"),
+            Type::Enum(name) => write!(f, "{}", name),
+            Type::Unknown => write!(f, "Wax"),
+        }
+    }
+}
+
+// A gradual type checker. Types come from literals and from annotations on
+// `weel` declarations, parameters and return types; everything else is
+// `Wax` and never reported. All mismatches are collected before failing.
+pub struct Checker {
+    scopes: Vec<HashMap<String, Type>>,
+    enums: HashSet<String>,
+    functions: Vec<(String, Type)>,
+    errors: Vec<Error>,
+}
+
+impl Checker {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+            enums: HashSet::new(),
+            functions: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn check(&mut self, statements: &[Stmt]) -> Result<()> {
+        self.check_statements(statements);
+
+        if !self.errors.is_empty() {
+            let errors = std::mem::take(&mut self.errors);
+            anyhow::bail!(Errors(errors));
+        }
+
+        Ok(())
+    }
+
+    fn check_statements(&mut self, statements: &[Stmt]) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Stmt) {
+        match statement {
+            Stmt::Expression { expression } | Stmt::Print { expression } => {
+                self.type_of(expression);
+            }
+            Stmt::Var {
+                pattern,
+                annotation,
+                initializer,
+                ..
+            } => {
+                let actual = match initializer {
+                    Some(initializer) => self.type_of(initializer),
+                    None => Type::Nil,
+                };
+                let declared = self.annotated(annotation);
+
+                if let (Pattern::Identifier(name), Some(_)) = (pattern, annotation) {
+                    if !declared.accepts(&actual) {
+                        self.error(
+                            name,
+                            Message::InitializerType {
+                                name: name.lexeme.clone(),
+                                declared: declared.to_string(),
+                                actual: actual.to_string(),
+                            },
+                        );
+                    }
+                }
+                self.declare_pattern(pattern, declared);
+            }
+            Stmt::Const { name, initializer } => {
+                let actual = self.type_of(initializer);
+                self.declare(name, actual);
+            }
+            Stmt::Fun {
+                name,
+                params,
+                return_type,
+                body,
+                ..
+            } => {
+                let params_types = params
+                    .iter()
+                    .map(|param| self.annotated(&param.annotation))
+                    .collect::<Vec<Type>>();
+                let returns = self.annotated(return_type);
+                let function = Type::Function {
+                    params: params_types.clone(),
+                    returns: Box::new(returns.clone()),
+                };
+                self.declare(name, function);
+
+                self.functions.push((name.lexeme.clone(), returns));
+                self.scopes.push(HashMap::new());
+                for (param, expected) in params.iter().zip(params_types) {
+                    self.check_param(name, param, expected);
+                }
+                self.check_statements(body);
+                self.scopes.pop();
+                self.functions.pop();
+            }
+            Stmt::Enum { name, .. } => {
+                self.enums.insert(name.lexeme.clone());
+                self.declare(name, Type::Unknown);
+            }
+            Stmt::Block { statements } => {
+                self.scopes.push(HashMap::new());
+                self.check_statements(statements);
+                self.scopes.pop();
+            }
+            Stmt::ForEach {
+                name,
+                iterable,
+                body,
+            } => {
+                self.type_of(iterable);
+                self.scopes.push(HashMap::new());
+                self.declare(name, Type::Unknown);
+                self.check_statements(body);
+                self.scopes.pop();
+            }
+            Stmt::Match { subject, arms, .. } => {
+                self.type_of(subject);
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    for binding in arm.case.bindings() {
+                        self.declare(binding, Type::Unknown);
+                    }
+                    self.check_statements(&arm.body);
+                    self.scopes.pop();
+                }
+            }
+            Stmt::Yield { value, .. } | Stmt::Spawn { call: value, .. } => {
+                self.type_of(value);
+            }
+            Stmt::Return { keyword, value } => {
+                let actual = match value {
+                    Some(value) => self.type_of(value),
+                    None => Type::Nil,
+                };
+                if let Some((function, expected)) = self.functions.last().cloned() {
+                    if !expected.accepts(&actual) {
+                        self.error(
+                            keyword,
+                            Message::ReturnType {
+                                function,
+                                expected: expected.to_string(),
+                                actual: actual.to_string(),
+                            },
+                        );
+                    }
+                }
+            }
+            Stmt::Send { value, channel, .. } => {
+                self.type_of(value);
+                self.type_of(channel);
+            }
+            Stmt::Receive { name, channel, .. } => {
+                self.type_of(channel);
+                self.declare(name, Type::Unknown);
+            }
+        }
+    }
+
+    fn check_param(&mut self, function: &Token, param: &PatternItem, expected: Type) {
+        if let Some(default) = &param.default {
+            let actual = self.type_of(default);
+            if !expected.accepts(&actual) {
+                let names = param.pattern.names();
+                let at = names.first().copied().unwrap_or(function);
+                self.error(
+                    at,
+                    Message::DefaultType {
+                        parameter: at.lexeme.clone(),
+                        function: function.lexeme.clone(),
+                        expected: expected.to_string(),
+                        actual: actual.to_string(),
+                    },
+                );
+            }
+        }
+        self.declare_pattern(&param.pattern, expected);
+    }
+
+    fn type_of(&mut self, expression: &Expr) -> Type {
+        match expression {
+            Expr::Literal { value } => Type::of_value(value),
+            Expr::Variable { name } => self.lookup(name),
+            Expr::Grouping { expression } => self.type_of(expression),
+            Expr::Assignment { name, value } => {
+                let actual = self.type_of(value);
+                let declared = self.lookup(name);
+                if !declared.accepts(&actual) {
+                    self.error(
+                        name,
+                        Message::AssignmentType {
+                            name: name.lexeme.clone(),
+                            declared: declared.to_string(),
+                            actual: actual.to_string(),
+                        },
+                    );
+                }
+                actual
+            }
+            Expr::Unary { operator, right } => {
+                let right = self.type_of(right);
+                match operator.token_type {
+                    TokenType::Bang => Type::Bool,
+                    _ if !Type::Number.accepts(&right) => {
+                        self.error(operator, Message::CannotNegate(right.to_string()));
+                        Type::Unknown
+                    }
+                    _ => Type::Number,
+                }
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.type_of(left);
+                let right = self.type_of(right);
+                self.binary(operator, left, right)
+            }
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.type_of(left);
+                let right = self.type_of(right);
+                match left {
+                    Type::Nil if operator.token_type != TokenType::And => right,
+                    _ if operator.token_type != TokenType::QuestionQuestion && left != right => {
+                        Type::Unknown
+                    }
+                    Type::Unknown => Type::Unknown,
+                    left => left,
+                }
+            }
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.type_of(condition);
+                let then_type = self.type_of(then_branch);
+                let else_type = self.type_of(else_branch);
+                if then_type == else_type {
+                    then_type
+                } else {
+                    Type::Unknown
+                }
+            }
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => {
+                let callee_type = self.type_of(callee);
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.type_of(argument))
+                    .collect::<Vec<Type>>();
+
+                match callee_type {
+                    Type::Function { params, returns } => {
+                        let name = match callee.as_ref() {
+                            Expr::Variable { name } => name.lexeme.clone(),
+                            _ => "function".to_string(),
+                        };
+                        for (index, (expected, actual)) in params.iter().zip(&arguments).enumerate()
+                        {
+                            if !expected.accepts(actual) {
+                                self.error(
+                                    paren,
+                                    Message::ArgumentType {
+                                        index: index + 1,
+                                        function: name.clone(),
+                                        expected: expected.to_string(),
+                                        actual: actual.to_string(),
+                                    },
+                                );
+                            }
+                        }
+                        *returns
+                    }
+                    Type::Unknown => Type::Unknown,
+                    other => {
+                        self.error(paren, Message::CannotCall(other.to_string()));
+                        Type::Unknown
+                    }
+                }
+            }
+            Expr::Get { object, .. } => {
+                self.type_of(object);
+                Type::Unknown
+            }
+            Expr::Index { object, index, .. } => {
+                let object = self.type_of(object);
+                self.type_of(index);
+                match object {
+                    Type::String => Type::String,
+                    _ => Type::Unknown,
+                }
+            }
+            Expr::List { elements } => {
+                for element in elements {
+                    self.type_of(element);
+                }
+                Type::List
+            }
+            Expr::Tuple { elements } => {
+                for element in elements {
+                    self.type_of(element);
+                }
+                Type::Tuple
+            }
+        }
+    }
+
+    fn binary(&mut self, operator: &Token, left: Type, right: Type) -> Type {
+        let operation = match operator.token_type {
+            TokenType::EqualEqual | TokenType::BangEqual => return Type::Bool,
+            TokenType::DotDot | TokenType::DotDotEqual => return Type::Unknown,
+            TokenType::Plus => Operation::Add,
+            TokenType::Minus => Operation::Subtract,
+            TokenType::Star => Operation::Multiply,
+            TokenType::Slash => Operation::Divide,
+            _ => Operation::Compare,
+        };
+
+        if !left.is_known() || !right.is_known() {
+            return match operation {
+                Operation::Compare => Type::Bool,
+                _ => Type::Unknown,
+            };
+        }
+
+        let result = match (operation, &left, &right) {
+            (Operation::Compare, Type::Number, Type::Number)
+            | (Operation::Compare, Type::Tuple, Type::Tuple)
+            | (Operation::Compare, Type::Set, Type::Set) => Some(Type::Bool),
+            (Operation::Add, Type::String, Type::String) => Some(Type::String),
+            (Operation::Add | Operation::Subtract | Operation::Multiply, Type::Set, Type::Set) => {
+                Some(Type::Set)
+            }
+            (Operation::Compare, _, _) => None,
+            (_, Type::Number, Type::Number) => Some(Type::Number),
+            _ => None,
+        };
+
+        match result {
+            Some(result) => result,
+            None => {
+                self.error(
+                    operator,
+                    Message::CannotApply {
+                        operation,
+                        left: left.to_string(),
+                        right: right.to_string(),
+                    },
+                );
+                Type::Unknown
+            }
+        }
+    }
+
+    fn annotated(&mut self, annotation: &Option<Token>) -> Type {
+        let name = match annotation {
+            Some(name) => name,
+            None => return Type::Unknown,
+        };
+
+        match name.lexeme.as_str() {
+            "Tiro" => Type::Number,
+            "Qoraal" => Type::String,
+            "Xaqiiq" => Type::Bool,
+            "Waxba" => Type::Nil,
+            "Liis" => Type::List,
+            "Koox" => Type::Tuple,
+            "Urur" => Type::Set,
+            "Hawl" => Type::Function {
+                params: Vec::new(),
+                returns: Box::new(Type::Unknown),
+            },
+            "Wax" => Type::Unknown,
+            enumeration if self.enums.contains(enumeration) => Type::Enum(enumeration.to_string()),
+            _ => {
+                self.error(name, Message::UnknownType(name.lexeme.clone()));
+                Type::Unknown
+            }
+        }
+    }
+
+    fn declare_pattern(&mut self, pattern: &Pattern, declared: Type) {
+        match pattern {
+            Pattern::Identifier(name) => self.declare(name, declared),
+            _ => {
+                for name in pattern.names() {
+                    self.declare(name, Type::Unknown);
+                }
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Token, declared: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), declared);
+        }
+    }
+
+    fn lookup(&self, name: &Token) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).cloned())
+            .unwrap_or(Type::Unknown)
+    }
+
+    fn error(&mut self, at: &Token, message: Message) {
+        self.errors.push(Error::check(message).at(at));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn check(source: &str) -> Result<()> {
+        let tokens = Lexer::new(source).lex()?;
+        let statements = Parser::new(tokens).parse()?;
+        Checker::new().check(&statements)
+    }
+
+    // Each error with the line its span starts on.
+    fn errors(source: &str) -> Vec<String> {
+        let error = check(source).unwrap_err();
+        let errors = error.downcast::<Errors>().unwrap();
+        errors
+            .0
+            .iter()
+            .map(|error| format!("{}: {}", error.span.unwrap().line + 1, error))
+            .collect()
+    }
+
+    #[test]
+    fn test_annotated_mismatches() {
+        let errors = errors(
+            "weel da: Tiro = \"saddex\";
+             hawl salaan(magac: Qoraal, tirada: Tiro = 1): Qoraal { celi tirada; }
+             weel r = salaan(5);
+             da = run;
+             weel x = da + \"sano\";",
+        );
+
+        assert_eq!(
+            errors,
+            [
+                "1: Cannot initialise 'da' of type Tiro with Qoraal",
+                "2: 'salaan' returns Qoraal but got Tiro",
+                "3: Argument 1 of 'salaan' expects Qoraal but got Tiro",
+                "4: Cannot assign Xaqiiq to 'da' of type Tiro",
+                "5: Cannot add Tiro and Qoraal",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unannotated_code_is_skipped() {
+        check(
+            "nooc Xaalad { Sugan }
+             hawl f(a, b) { celi a + b; }
+             weel x = f(1, \"laba\");
+             weel y: Xaalad = Xaalad.Sugan;
+             weel z: Tiro = x;",
+        )
+        .unwrap();
+
+        assert_eq!(errors("weel x: Lambar = 1;"), ["1: Unknown type 'Lambar'"]);
+    }
+}
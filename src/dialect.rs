@@ -0,0 +1,187 @@
+use crate::lexer::TokenType;
+
+/// A spelling of the language's keywords. Each keyword may have several
+/// spellings; the first is the one diagnostics use.
+#[derive(Debug, PartialEq)]
+pub struct Dialect {
+    pub names: &'static [&'static str],
+    keywords: &'static [(TokenType, &'static [&'static str])],
+}
+
+pub const SOMALI: Dialect = Dialect {
+    names: &["so", "somali", "soomaali"],
+    keywords: &[
+        (TokenType::And, &["iyo"]),
+        (TokenType::Class, &["qeyb"]),
+        (TokenType::Const, &["joogto"]),
+        (TokenType::Else, &["kale"]),
+        (TokenType::Enum, &["nooc"]),
+        (TokenType::False, &["been"]),
+        (TokenType::For, &["markasta"]),
+        (TokenType::From, &["ka"]),
+        (TokenType::Fun, &["hawl"]),
+        (TokenType::If, &["haddii", "hadii"]),
+        (TokenType::In, &["ku"]),
+        (TokenType::Match, &["dooro"]),
+        (TokenType::Nil, &["waxba"]),
+        (TokenType::Or, &["ama"]),
+        (TokenType::Print, &["daabac"]),
+        (TokenType::Receive, &["qaado"]),
+        (TokenType::Return, &["celi"]),
+        (TokenType::Send, &["dir"]),
+        (TokenType::Spawn, &["bilow"]),
+        (TokenType::Super, &["super"]),
+        (TokenType::This, &["kan"]),
+        (TokenType::True, &["run"]),
+        (TokenType::Var, &["weel"]),
+        (TokenType::While, &["inta"]),
+        (TokenType::Yield, &["bixi"]),
+    ],
+};
+
+pub const ENGLISH: Dialect = Dialect {
+    names: &["en", "english", "ingiriisi"],
+    keywords: &[
+        (TokenType::And, &["and"]),
+        (TokenType::Class, &["class"]),
+        (TokenType::Const, &["const"]),
+        (TokenType::Else, &["else"]),
+        (TokenType::Enum, &["enum"]),
+        (TokenType::False, &["false"]),
+        (TokenType::For, &["for"]),
+        (TokenType::From, &["from"]),
+        (TokenType::Fun, &["fun", "function"]),
+        (TokenType::If, &["if"]),
+        (TokenType::In, &["in", "to"]),
+        (TokenType::Match, &["match"]),
+        (TokenType::Nil, &["nil"]),
+        (TokenType::Or, &["or"]),
+        (TokenType::Print, &["print"]),
+        (TokenType::Receive, &["receive"]),
+        (TokenType::Return, &["return"]),
+        (TokenType::Send, &["send"]),
+        (TokenType::Spawn, &["spawn"]),
+        (TokenType::Super, &["super"]),
+        (TokenType::This, &["this"]),
+        (TokenType::True, &["true"]),
+        (TokenType::Var, &["var"]),
+        (TokenType::While, &["while"]),
+        (TokenType::Yield, &["yield"]),
+    ],
+};
+
+// The Somali keywords written in the Osmanya script.
+pub const OSMANYA: Dialect = Dialect {
+    names: &["os", "osmanya", "cismaaniya"],
+    keywords: &[
+        (TokenType::And, &["𐒘𐒕𐒙"]),
+        (TokenType::Class, &["𐒎𐒗𐒕𐒁"]),
+        (TokenType::Const, &["𐒃𐒝𐒌𐒂𐒙"]),
+        (TokenType::Else, &["𐒏𐒖𐒐𐒗"]),
+        (TokenType::Enum, &["𐒒𐒝𐒋"]),
+        (TokenType::False, &["𐒁𐒜𐒒"]),
+        (TokenType::For, &["𐒑𐒖𐒇𐒏𐒖𐒈𐒂𐒖"]),
+        (TokenType::From, &["𐒏𐒖"]),
+        (TokenType::Fun, &["𐒔𐒖𐒓𐒐"]),
+        (TokenType::If, &["𐒔𐒖𐒆𐒆𐒕", "𐒔𐒖𐒆𐒕"]),
+        (TokenType::In, &["𐒏𐒚"]),
+        (TokenType::Match, &["𐒆𐒝𐒇𐒙"]),
+        (TokenType::Nil, &["𐒓𐒖𐒄𐒁𐒖"]),
+        (TokenType::Or, &["𐒖𐒑𐒖"]),
+        (TokenType::Print, &["𐒆𐒛𐒁𐒖𐒋"]),
+        (TokenType::Receive, &["𐒎𐒛𐒆𐒙"]),
+        (TokenType::Return, &["𐒋𐒗𐒐𐒘"]),
+        (TokenType::Send, &["𐒆𐒘𐒇"]),
+        (TokenType::Spawn, &["𐒁𐒘𐒐𐒙𐒓"]),
+        (TokenType::Super, &["𐒈𐒚𐒁𐒗𐒇"]),
+        (TokenType::This, &["𐒏𐒖𐒒"]),
+        (TokenType::True, &["𐒇𐒚𐒒"]),
+        (TokenType::Var, &["𐒓𐒜𐒐"]),
+        (TokenType::While, &["𐒘𐒒𐒂𐒖"]),
+        (TokenType::Yield, &["𐒁𐒘𐒄𐒘"]),
+    ],
+};
+
+pub const DIALECTS: &[&Dialect] = &[&SOMALI, &ENGLISH, &OSMANYA];
+
+impl Dialect {
+    pub fn from_name(name: &str) -> Option<&'static Dialect> {
+        let name = name.to_lowercase();
+        DIALECTS
+            .iter()
+            .find(|dialect| dialect.names.contains(&name.as_str()))
+            .copied()
+    }
+
+    // A file can choose its own dialect with a first line such as
+    // `// lahjad: en`, which wins over the one it would otherwise get.
+    pub fn for_source(source: &str, default: &'static Dialect) -> &'static Dialect {
+        let pragma = source
+            .lines()
+            .next()
+            .and_then(|line| line.trim().strip_prefix("//"))
+            .and_then(|comment| comment.trim().strip_prefix("lahjad:"));
+
+        match pragma {
+            Some(name) => Self::from_name(name.trim()).unwrap_or(default),
+            None => default,
+        }
+    }
+
+    pub fn keyword(&self, text: &str) -> Option<TokenType> {
+        self.keywords
+            .iter()
+            .find(|(_, spellings)| spellings.contains(&text))
+            .map(|(token_type, _)| *token_type)
+    }
+
+    pub fn spelling(&self, token_type: TokenType) -> &'static str {
+        self.keywords
+            .iter()
+            .find(|(keyword, _)| *keyword == token_type)
+            .map(|(_, spellings)| spellings[0])
+            .unwrap_or("?")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn types(source: &str) -> Vec<TokenType> {
+        Lexer::new(source)
+            .lex()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.token_type)
+            .collect()
+    }
+
+    #[test]
+    fn test_pragma_selects_dialect() {
+        assert_eq!(
+            types("// lahjad: en\nvar x = true;"),
+            types("weel x = run;")
+        );
+        assert_eq!(
+            types("// lahjad: english\nsend 1 to ch;"),
+            types("dir 1 ku ch;")
+        );
+        assert_eq!(types("var")[0], TokenType::Identifier);
+        assert_eq!(
+            types("// lahjad: os\n\u{10493}\u{1049c}\u{10490} x = \u{104a7};"),
+            types("weel x = 7;")
+        );
+    }
+
+    #[test]
+    fn test_every_dialect_spells_every_keyword() {
+        for dialect in DIALECTS {
+            for (token_type, _) in SOMALI.keywords {
+                let spelling = dialect.spelling(*token_type);
+                assert_eq!(dialect.keyword(spelling), Some(*token_type));
+            }
+        }
+    }
+}
@@ -1,26 +1,39 @@
 use std::collections::HashMap;
 
-use crate::expr::ExpLiteralValue;
+use crate::{
+    error::{Error, Result},
+    expr::ExpLiteralValue,
+    message::Message,
+};
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+struct Binding {
+    value: ExpLiteralValue,
+    constant: Option<usize>,
+}
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Environment {
-    values: HashMap<String, ExpLiteralValue>,
+    values: HashMap<String, Binding>,
     enclosing: Option<Box<Environment>>,
 }
 
 impl PartialOrd for Environment {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         match self.values.iter().partial_cmp(&other.values) {
-            Some(std::cmp::Ordering::Equal) => match self.enclosing.partial_cmp(&other.enclosing) {
-                Some(ordering) => Some(ordering),
-                None => None,
-            },
+            Some(std::cmp::Ordering::Equal) => self.enclosing.partial_cmp(&other.enclosing),
             Some(ordering) => Some(ordering),
             None => None,
         }
     }
 }
 
+impl Default for Environment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Environment {
     pub fn new() -> Self {
         Self {
@@ -36,14 +49,34 @@ impl Environment {
         }
     }
 
+    pub fn into_enclosing(self) -> Option<Environment> {
+        self.enclosing.map(|enclosing| *enclosing)
+    }
+
     pub fn define(&mut self, name: &str, value: ExpLiteralValue) {
-        self.values.insert(name.to_string(), value);
+        self.values.insert(
+            name.to_string(),
+            Binding {
+                value,
+                constant: None,
+            },
+        );
+    }
+
+    // `line` is where the constant was declared, for error messages.
+    pub fn define_constant(&mut self, name: &str, value: ExpLiteralValue, line: usize) {
+        self.values.insert(
+            name.to_string(),
+            Binding {
+                value,
+                constant: Some(line),
+            },
+        );
     }
+
     pub fn get(&self, name: &str) -> Option<&ExpLiteralValue> {
         match self.values.get(name) {
-            Some(value) => {
-                return Some(value);
-            }
+            Some(binding) => Some(&binding.value),
             None => match &self.enclosing {
                 Some(enclosing) => enclosing.get(name),
                 None => None,
@@ -51,14 +84,25 @@ impl Environment {
         }
     }
 
-    pub fn assign(&mut self, name: &str, value: ExpLiteralValue) {
-        if self.values.contains_key(name) {
-            self.values.insert(name.to_string(), value);
-        } else {
-            match &mut self.enclosing {
-                Some(enclosing) => enclosing.assign(name, value),
-                None => (),
+    pub fn assign(&mut self, name: &str, value: ExpLiteralValue) -> Result<()> {
+        match self.values.get_mut(name) {
+            Some(Binding {
+                constant: Some(line),
+                ..
+            }) => {
+                return Err(Error::runtime(Message::AssignToConstant {
+                    name: name.to_string(),
+                    line: *line + 1,
+                }))
+            }
+            Some(binding) => binding.value = value,
+            None => {
+                if let Some(enclosing) = &mut self.enclosing {
+                    enclosing.assign(name, value)?;
+                }
             }
         }
+
+        Ok(())
     }
 }
@@ -0,0 +1,389 @@
+use crate::{
+    dialect::{Dialect, SOMALI},
+    lexer::Token,
+    message::{Language, Message},
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    Lex,
+    Parse,
+    Resolve,
+    Check,
+    Runtime,
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", Language::English.kind(*self))
+    }
+}
+
+// Codes name the problem rather than the stage that found it, so a
+// constant assignment is E0201 whether the resolver or the interpreter
+// catches it. Tools match on them: never renumber an existing code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    UnexpectedCharacter,
+    UnterminatedString,
+    InvalidNumber,
+    InvalidEscape,
+    UnterminatedComment,
+    UnexpectedToken,
+    ExpectedToken,
+    InvalidPattern,
+    InvalidStatement,
+    AssignToConstant,
+    UndefinedVariable,
+    UndefinedProperty,
+    InvalidOperand,
+    DivisionByZero,
+    IndexOutOfRange,
+    NotCallable,
+    ArgumentCount,
+    NotIterable,
+    PatternMismatch,
+    ControlFlow,
+    Deadlock,
+    TypeMismatch,
+    UnknownType,
+}
+
+impl ErrorCode {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorCode::UnexpectedCharacter => "E0001",
+            ErrorCode::UnterminatedString => "E0002",
+            ErrorCode::InvalidNumber => "E0003",
+            ErrorCode::InvalidEscape => "E0004",
+            ErrorCode::UnterminatedComment => "E0005",
+            ErrorCode::UnexpectedToken => "E0101",
+            ErrorCode::ExpectedToken => "E0102",
+            ErrorCode::InvalidPattern => "E0103",
+            ErrorCode::InvalidStatement => "E0104",
+            ErrorCode::AssignToConstant => "E0201",
+            ErrorCode::UndefinedVariable => "E0301",
+            ErrorCode::UndefinedProperty => "E0302",
+            ErrorCode::InvalidOperand => "E0303",
+            ErrorCode::DivisionByZero => "E0304",
+            ErrorCode::IndexOutOfRange => "E0305",
+            ErrorCode::NotCallable => "E0306",
+            ErrorCode::ArgumentCount => "E0307",
+            ErrorCode::NotIterable => "E0308",
+            ErrorCode::PatternMismatch => "E0309",
+            ErrorCode::ControlFlow => "E0310",
+            ErrorCode::Deadlock => "E0311",
+            ErrorCode::TypeMismatch => "E0401",
+            ErrorCode::UnknownType => "E0402",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub offset: usize,
+    pub length: usize,
+}
+
+impl Span {
+    pub fn of(token: &Token) -> Self {
+        Self {
+            line: token.line_number,
+            column: token.column,
+            offset: token.offset,
+            length: token.lexeme.len(),
+        }
+    }
+
+    // The empty span just past a token, where something was expected but
+    // not found.
+    pub fn after(token: &Token) -> Self {
+        match token.lexeme.contains('\n') {
+            true => Self::of(token),
+            false => Self {
+                line: token.line_number,
+                column: token.column + token.lexeme.len(),
+                offset: token.offset + token.lexeme.len(),
+                length: 0,
+            },
+        }
+    }
+}
+
+/// An error from any stage of running a program. `Display` prints only the
+/// message, in English, so callers decide how to show the code, location and
+/// language. The message is boxed to keep `Result<_, Error>` small.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub code: ErrorCode,
+    pub message: Box<Message>,
+    pub span: Option<Span>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: Message) -> Self {
+        Self {
+            kind,
+            code: message.code(),
+            message: Box::new(message),
+            span: None,
+        }
+    }
+
+    pub fn lex(message: Message) -> Self {
+        Self::new(ErrorKind::Lex, message)
+    }
+
+    pub fn parse(message: Message) -> Self {
+        Self::new(ErrorKind::Parse, message)
+    }
+
+    pub fn resolve(message: Message) -> Self {
+        Self::new(ErrorKind::Resolve, message)
+    }
+
+    pub fn check(message: Message) -> Self {
+        Self::new(ErrorKind::Check, message)
+    }
+
+    pub fn runtime(message: Message) -> Self {
+        Self::new(ErrorKind::Runtime, message)
+    }
+
+    pub fn at(self, token: &Token) -> Self {
+        self.with_span(Span::of(token))
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span = Some(span);
+        self
+    }
+
+    /// A multi-line diagnostic quoting the offending line of `source` with a
+    /// caret under the span, optionally coloured with ANSI escapes.
+    pub fn render(
+        &self,
+        source: &str,
+        file: &str,
+        colour: bool,
+        language: Language,
+        dialect: &Dialect,
+    ) -> String {
+        let paint = |style: &str, text: &str| match colour {
+            true => format!("\x1b[{}m{}\x1b[0m", style, text),
+            false => text.to_string(),
+        };
+
+        let mut out = format!(
+            "{}: {}\n",
+            paint(
+                "1;31",
+                &format!("{}[{}]", language.kind(self.kind), self.code.code())
+            ),
+            paint("1", &self.message.text(language, dialect))
+        );
+
+        let Some(span) = self.span else {
+            out.push_str(&format!("{} {}\n", paint("1;34", "-->"), file));
+            return out;
+        };
+
+        let number = (span.line + 1).to_string();
+        let gutter = " ".repeat(number.len());
+        let bar = paint("1;34", "|");
+        let text = source.lines().nth(span.line).unwrap_or("");
+        let column = floor_char_boundary(text, span.column);
+
+        // Copy tabs from the source so the caret lines up however wide the
+        // terminal draws them.
+        let padding: String = text[..column]
+            .chars()
+            .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+            .collect();
+        let end = floor_char_boundary(text, column + span.length);
+        let width = text[column..end].chars().count().max(1);
+
+        out.push_str(&format!(
+            "{}{} {}:{}:{}\n",
+            gutter,
+            paint("1;34", "-->"),
+            file,
+            number,
+            text[..column].chars().count() + 1
+        ));
+        out.push_str(&format!("{} {}\n", gutter, bar));
+        out.push_str(&format!("{} {} {}\n", paint("1;34", &number), bar, text));
+        out.push_str(&format!(
+            "{} {} {}{}\n",
+            gutter,
+            bar,
+            padding,
+            paint("1;31", &"^".repeat(width))
+        ));
+        if let Some(help) = language.help(self.code, dialect) {
+            out.push_str(&format!(
+                "{} {} {}\n",
+                gutter,
+                paint("1;34", "="),
+                paint("1", &format!("{}: {}", language.help_label(), help))
+            ));
+        }
+
+        out
+    }
+
+    // Errors raised deep inside a call often have no token to point at; the
+    // nearest enclosing expression fills in its own.
+    pub fn or_at(self, token: &Token) -> Self {
+        match self.span {
+            Some(_) => self,
+            None => self.at(token),
+        }
+    }
+}
+
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.message.text(Language::English, &SOMALI))
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Every error found by a stage that recovers and carries on, such as the
+/// parser, in the order they were found. Never empty.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Errors(pub Vec<Error>);
+
+impl std::fmt::Display for Errors {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let messages: Vec<String> = self.0.iter().map(|error| error.to_string()).collect();
+        write!(f, "{}", messages.join("\n"))
+    }
+}
+
+impl std::error::Error for Errors {}
+
+// Callers that can only handle one error get the first, which is the one
+// least likely to be a consequence of another.
+impl From<Errors> for Error {
+    fn from(errors: Errors) -> Self {
+        errors.0.into_iter().next().unwrap()
+    }
+}
+
+pub trait ResultExt<T> {
+    fn or_at(self, token: &Token) -> Result<T>;
+}
+
+impl<T, E: Into<Error>> ResultExt<T> for std::result::Result<T, E> {
+    fn or_at(self, token: &Token) -> Result<T> {
+        self.map_err(|error| error.into().or_at(token))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{environment::Environment, lexer::Lexer, parser::Parser, resolver::Resolver};
+
+    fn run(source: &str) -> Result<()> {
+        let tokens = Lexer::new(source).lex()?;
+        let statements = Parser::new(tokens).parse()?;
+        Resolver::new().resolve(&statements)?;
+
+        let mut env = Environment::new();
+        for statement in statements {
+            statement.execute(&mut env)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_kinds_codes_and_spans() {
+        let cases = [
+            ("weel x = 1;\nweel y = #;", ErrorKind::Lex, "E0001", (1, 9)),
+            (
+                "weel x = 1\nweel y = 2;",
+                ErrorKind::Parse,
+                "E0102",
+                (0, 10),
+            ),
+            (
+                "joogto x = 1;\n\nx = 2;",
+                ErrorKind::Resolve,
+                "E0201",
+                (2, 0),
+            ),
+            (
+                "weel x = 1;\nweel y = x / 0;",
+                ErrorKind::Runtime,
+                "E0304",
+                (1, 11),
+            ),
+            (
+                "weel x = [1];\nweel y = x[3];",
+                ErrorKind::Runtime,
+                "E0305",
+                (1, 12),
+            ),
+            (
+                "hawl f(a) {}\nweel y = f();",
+                ErrorKind::Runtime,
+                "E0307",
+                (1, 11),
+            ),
+            ("markasta x ku 5 {}", ErrorKind::Runtime, "E0308", (0, 9)),
+            ("weel [a] = 5;", ErrorKind::Runtime, "E0309", (0, 5)),
+            ("weel {b} = 5;", ErrorKind::Runtime, "E0309", (0, 5)),
+        ];
+
+        for (source, kind, code, position) in cases {
+            let error = run(source).unwrap_err();
+            assert_eq!(
+                (
+                    error.kind,
+                    error.code.code(),
+                    error.span.map(|span| (span.line, span.column))
+                ),
+                (kind, code, Some(position)),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_points_at_the_span() {
+        let source = "weel x = 1;\n\tweel y = x / 0;\n";
+        let error = run(source).unwrap_err();
+
+        assert_eq!(
+            error.render(source, "tusaale.mali", false, Language::English, &SOMALI),
+            "runtime error[E0304]: Cannot divide by zero
+ --> tusaale.mali:2:13
+  |
+2 | \tweel y = x / 0;
+  | \t           ^
+  = help: check that the divisor is not zero first
+"
+        );
+        assert!(error
+            .render(source, "tusaale.mali", true, Language::English, &SOMALI)
+            .contains("\x1b[1;31m^\x1b[0m"));
+    }
+}
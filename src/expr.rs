@@ -1,11 +1,22 @@
 use core::panic;
 
 use crate::{
+    channel::Channel,
     environment::Environment,
+    error::{Error, Result, ResultExt},
+    generator::Generator,
+    iterator::Range,
     lexer::{LiteralValue, Token, TokenType},
-    statement::Function,
+    message::{Collection, Message, Operation},
+    native::{BoundMethod, NativeFunction},
+    numerals::Numerals,
+    set::Set,
+    statement::{Constructor, Enum, Function, Variant},
+};
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
 };
-use anyhow::Result;
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub enum ExpLiteralValue {
@@ -15,22 +26,71 @@ pub enum ExpLiteralValue {
     False,
     Nil,
     FunctionValue(Function),
+    EnumValue(Enum),
+    ConstructorValue(Constructor),
+    VariantValue(Variant),
+    ListValue(Vec<ExpLiteralValue>),
+    RangeValue(Range),
+    NativeValue(NativeFunction),
+    MethodValue(BoundMethod),
+    GeneratorValue(Generator),
+    ChannelValue(Channel),
+    TupleValue(Vec<ExpLiteralValue>),
+    SetValue(Set),
 }
 
 use ExpLiteralValue::*;
 
-impl ExpLiteralValue {
-    pub fn to_string(&self) -> String {
+impl std::fmt::Display for ExpLiteralValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
-            ExpLiteralValue::FunctionValue(f) => todo!(),
-            ExpLiteralValue::Number(n) => n.to_string(),
-            ExpLiteralValue::StringValue(s) => s.clone(),
-            ExpLiteralValue::True => "true".to_string(),
-            ExpLiteralValue::False => "false".to_string(),
-            ExpLiteralValue::Nil => "nil".to_string(),
+            ExpLiteralValue::FunctionValue(function) => write!(f, "<hawl {}>", function.name()),
+            ExpLiteralValue::EnumValue(enumeration) => write!(f, "<nooc {}>", enumeration.name()),
+            ExpLiteralValue::ConstructorValue(constructor) => {
+                write!(f, "<nooc {}>", constructor.name())
+            }
+            ExpLiteralValue::VariantValue(variant) => write!(f, "{}", variant),
+            ExpLiteralValue::ListValue(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                write!(f, "[{}]", items)
+            }
+            ExpLiteralValue::RangeValue(range) => write!(f, "{}", range),
+            ExpLiteralValue::NativeValue(native) => write!(f, "<hawl {}>", native.name()),
+            ExpLiteralValue::MethodValue(method) => write!(f, "<hawl {}>", method.name()),
+            ExpLiteralValue::GeneratorValue(generator) => {
+                write!(f, "<generator {}>", generator.name())
+            }
+            ExpLiteralValue::ChannelValue(channel) => write!(f, "{}", channel),
+            ExpLiteralValue::TupleValue(items) => {
+                let single = items.len() == 1;
+                let items = items
+                    .iter()
+                    .map(|item| item.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                if single {
+                    write!(f, "({},)", items)
+                } else {
+                    write!(f, "({})", items)
+                }
+            }
+            ExpLiteralValue::SetValue(set) => write!(f, "{}", set),
+            ExpLiteralValue::Number(n) => {
+                write!(f, "{}", Numerals::current().render(&n.to_string()))
+            }
+            ExpLiteralValue::StringValue(s) => write!(f, "{}", s),
+            ExpLiteralValue::True => write!(f, "true"),
+            ExpLiteralValue::False => write!(f, "false"),
+            ExpLiteralValue::Nil => write!(f, "nil"),
         }
     }
+}
 
+impl ExpLiteralValue {
     pub fn from_token(token: Token) -> Self {
         match token.token_type {
             TokenType::Number => match token.literal {
@@ -61,6 +121,16 @@ impl ExpLiteralValue {
     pub fn is_falsy(&self) -> ExpLiteralValue {
         match self {
             FunctionValue(_) => False,
+            EnumValue(_) => False,
+            ConstructorValue(_) => False,
+            VariantValue(_) => False,
+            NativeValue(_) => False,
+            MethodValue(_) => False,
+            GeneratorValue(_) => False,
+            ChannelValue(_) => False,
+            ListValue(items) | TupleValue(items) => ExpLiteralValue::from_bool(items.is_empty()),
+            SetValue(set) => ExpLiteralValue::from_bool(set.is_empty()),
+            RangeValue(range) => ExpLiteralValue::from_bool(range.is_empty()),
             Number(x) => {
                 if *x == 0.0 {
                     True
@@ -80,6 +150,91 @@ impl ExpLiteralValue {
             Nil => True,
         }
     }
+
+    // Values that can be set members: plain data, but not functions,
+    // generators or channels, whose identity can change under us.
+    pub fn is_hashable(&self) -> bool {
+        match self {
+            Number(_) | StringValue(_) | True | False | Nil | SetValue(_) => true,
+            TupleValue(items) => items.iter().all(|item| item.is_hashable()),
+            VariantValue(variant) => variant.is_hashable(),
+            _ => false,
+        }
+    }
+
+    // A total order over hashable values, used to keep sets sorted. Values of
+    // different kinds are ordered by kind; numbers use `f32::total_cmp` with
+    // -0 and 0 treated as equal.
+    pub fn total_cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Number(l), Number(r)) => normalize(*l).total_cmp(&normalize(*r)),
+            (StringValue(l), StringValue(r)) => l.cmp(r),
+            (TupleValue(l), TupleValue(r)) => lexicographic(l.iter(), r.iter()),
+            (SetValue(l), SetValue(r)) => lexicographic(l.iter(), r.iter()),
+            (VariantValue(l), VariantValue(r)) => l.total_cmp(r),
+            (l, r) => match l.kind().cmp(&r.kind()) {
+                Ordering::Equal => l.to_string().cmp(&r.to_string()),
+                ordering => ordering,
+            },
+        }
+    }
+
+    fn kind(&self) -> u8 {
+        match self {
+            Nil => 0,
+            False => 1,
+            True => 2,
+            Number(_) => 3,
+            StringValue(_) => 4,
+            TupleValue(_) => 5,
+            SetValue(_) => 6,
+            VariantValue(_) => 7,
+            _ => 8,
+        }
+    }
+}
+
+fn lexicographic<'a>(
+    mut left: impl Iterator<Item = &'a ExpLiteralValue>,
+    mut right: impl Iterator<Item = &'a ExpLiteralValue>,
+) -> Ordering {
+    loop {
+        match (left.next(), right.next()) {
+            (Some(l), Some(r)) => match l.total_cmp(r) {
+                Ordering::Equal => continue,
+                ordering => return ordering,
+            },
+            (Some(_), None) => return Ordering::Greater,
+            (None, Some(_)) => return Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        }
+    }
+}
+
+fn normalize(x: f32) -> f32 {
+    if x == 0.0 {
+        0.0
+    } else if x.is_nan() {
+        f32::NAN
+    } else {
+        x
+    }
+}
+
+// Consistent with both `PartialEq` and `total_cmp`: values that compare
+// equal hash equally. Values that are not hashable only hash their kind.
+impl Hash for ExpLiteralValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind().hash(state);
+        match self {
+            Number(x) => normalize(*x).to_bits().hash(state),
+            StringValue(s) => s.hash(state),
+            TupleValue(items) => items.hash(state),
+            SetValue(set) => set.hash(state),
+            VariantValue(variant) => variant.hash(state),
+            _ => (),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
@@ -111,14 +266,41 @@ pub enum Expr {
         paren: Token,
         arguments: Vec<Expr>,
     },
+    Get {
+        object: Box<Expr>,
+        name: Token,
+        optional: bool,
+    },
+    Index {
+        object: Box<Expr>,
+        bracket: Token,
+        index: Box<Expr>,
+        optional: bool,
+    },
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
+    Ternary {
+        condition: Box<Expr>,
+        then_branch: Box<Expr>,
+        else_branch: Box<Expr>,
+    },
+    List {
+        elements: Vec<Expr>,
+    },
+    Tuple {
+        elements: Vec<Expr>,
+    },
 }
 
-impl Expr {
-    pub fn to_string(&self) -> String {
+impl std::fmt::Display for Expr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             Expr::Call {
                 callee,
-                paren,
+                paren: _,
                 arguments,
             } => {
                 let arguments_str = arguments
@@ -126,64 +308,143 @@ impl Expr {
                     .map(|arg| arg.to_string())
                     .collect::<Vec<String>>()
                     .join(", ");
-                format!("({}({}))", callee.to_string(), arguments_str)
+                write!(f, "({}({}))", callee, arguments_str)
             }
-            Expr::Assignment { name, value } => format!("({:?} = {:?})", name, value.to_string()),
-            Expr::Variable { name } => format!("(var {})", name.lexeme),
-            Expr::Binary {
+            Expr::Get {
+                object,
+                name,
+                optional,
+            } => {
+                let operator = if *optional { "?." } else { "." };
+                write!(f, "({} {} {})", operator, object, name.lexeme)
+            }
+            Expr::Index {
+                object,
+                bracket: _,
+                index,
+                optional,
+            } => {
+                let operator = if *optional { "?.[]" } else { "[]" };
+                write!(f, "({} {} {})", operator, object, index)
+            }
+            Expr::Logical {
                 left,
                 operator,
                 right,
-            } => format!(
-                "({} {} {})",
-                operator.lexeme,
-                left.to_string(),
-                right.to_string()
-            ),
-            Expr::Grouping { expression } => format!("{}", (*expression).to_string()),
-            Expr::Literal { value } => format!("{}", value.to_string()),
-            Expr::Unary { operator, right } => {
-                let operator_str = operator.lexeme.clone();
-                let right_str = (*right).to_string();
-                format!("({} {})", operator_str, right_str)
+            } => write!(f, "({} {} {})", operator.lexeme, left, right),
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => write!(f, "(? {} {} {})", condition, then_branch, else_branch),
+            Expr::List { elements } => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                write!(f, "(list {})", elements)
+            }
+            Expr::Tuple { elements } => {
+                let elements = elements
+                    .iter()
+                    .map(|element| element.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                write!(f, "(tuple {})", elements)
             }
+            Expr::Assignment { name, value } => write!(f, "(= {} {})", name.lexeme, value),
+            Expr::Variable { name } => write!(f, "(var {})", name.lexeme),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => write!(f, "({} {} {})", operator.lexeme, left, right),
+            Expr::Grouping { expression } => write!(f, "{}", expression),
+            Expr::Literal { value } => write!(f, "{}", value),
+            Expr::Unary { operator, right } => write!(f, "({} {})", operator.lexeme, right),
         }
     }
+}
 
+impl Expr {
     pub fn evaluate(&self, env: &mut Environment) -> Result<ExpLiteralValue> {
         match self {
-            Expr::Call {
-                callee,
-                paren,
-                arguments,
+            Expr::Call { .. } | Expr::Get { .. } | Expr::Index { .. } => {
+                Ok(self.evaluate_link(env)?.unwrap_or(Nil))
+            }
+            Expr::Logical {
+                left,
+                operator,
+                right,
             } => {
-                let callee = callee.evaluate(env)?;
-                let arguments = arguments
+                let left_value = left.evaluate(env)?;
+
+                match operator.token_type {
+                    TokenType::QuestionQuestion => {
+                        if left_value == Nil {
+                            right.evaluate(env)
+                        } else {
+                            Ok(left_value)
+                        }
+                    }
+                    TokenType::Or => {
+                        if left_value.is_falsy() == True {
+                            right.evaluate(env)
+                        } else {
+                            Ok(left_value)
+                        }
+                    }
+                    TokenType::And => {
+                        if left_value.is_falsy() == True {
+                            Ok(left_value)
+                        } else {
+                            right.evaluate(env)
+                        }
+                    }
+                    _ => Err(
+                        Error::runtime(Message::InvalidOperator(operator.lexeme.clone()))
+                            .at(operator),
+                    ),
+                }
+            }
+            Expr::List { elements } => {
+                let items = elements
                     .iter()
-                    .map(|arg| arg.evaluate(env))
+                    .map(|element| element.evaluate(env))
                     .collect::<Result<Vec<ExpLiteralValue>>>()?;
-
-                match callee {
-                    FunctionValue(f) => f.call(arguments),
-                    _ => anyhow::bail!("Cannot call {:?}", callee),
+                Ok(ListValue(items))
+            }
+            Expr::Tuple { elements } => {
+                let items = elements
+                    .iter()
+                    .map(|element| element.evaluate(env))
+                    .collect::<Result<Vec<ExpLiteralValue>>>()?;
+                Ok(TupleValue(items))
+            }
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                if condition.evaluate(env)?.is_falsy() == False {
+                    then_branch.evaluate(env)
+                } else {
+                    else_branch.evaluate(env)
                 }
             }
             Expr::Variable { name } => match name.lexeme.as_str() {
                 "True" => Ok(ExpLiteralValue::True),
                 "False" => Ok(ExpLiteralValue::False),
                 "Nil" => Ok(ExpLiteralValue::Nil),
-                _ => {
-                    let result = env
-                        .get(&name.lexeme)
-                        .map(|v| v.clone())
-                        .ok_or_else(|| anyhow::anyhow!("Undefined variable {:?}", name.lexeme));
-
-                    result
-                }
+                _ => env.get(&name.lexeme).cloned().ok_or_else(|| {
+                    Error::runtime(Message::UndefinedVariable(name.lexeme.clone())).at(name)
+                }),
             },
             Expr::Assignment { name, value } => {
                 let value = value.evaluate(env)?;
-                env.assign(name.lexeme.as_str(), value.clone());
+                env.assign(name.lexeme.as_str(), value.clone())
+                    .map_err(|error| error.or_at(name))?;
                 Ok(value)
             }
             Expr::Literal { value } => Ok(value.clone()),
@@ -194,18 +455,17 @@ impl Expr {
                     (ExpLiteralValue::Number(x), TokenType::Minus) => {
                         Ok(ExpLiteralValue::Number(-x))
                     }
-                    (_, TokenType::Minus) => {
-                        anyhow::bail!("Cannot negate {:?}", right_value);
-                    }
+                    (_, TokenType::Minus) => Err(Error::runtime(Message::CannotNegate(
+                        right_value.to_string(),
+                    ))
+                    .at(operator)),
                     (any, TokenType::Bang) => {
                         Ok(ExpLiteralValue::from_bool(any.is_falsy() == True))
                     }
-                    (_, token_type) => {
-                        anyhow::bail!(
-                            "Cannot evaluate unary expression with operator {:?}",
-                            token_type,
-                        );
-                    }
+                    _ => Err(
+                        Error::runtime(Message::InvalidOperator(operator.lexeme.clone()))
+                            .at(operator),
+                    ),
                 }
             }
 
@@ -225,52 +485,62 @@ impl Expr {
                         (ExpLiteralValue::StringValue(l), ExpLiteralValue::StringValue(r)) => {
                             Ok(ExpLiteralValue::StringValue(format!("{}{}", l, r)))
                         }
-                        (l, r) => anyhow::bail!("Cannot add {:?} and {:?}", l, r),
+                        (SetValue(l), SetValue(r)) => Ok(SetValue(l.union(&r))),
+                        (l, r) => {
+                            Err(Error::runtime(cannot_apply(Operation::Add, &l, &r)).at(operator))
+                        }
                     },
                     TokenType::Minus => match (left_value, right_value) {
                         (ExpLiteralValue::Number(l), ExpLiteralValue::Number(r)) => {
                             Ok(ExpLiteralValue::Number(l - r))
                         }
-                        (l, r) => anyhow::bail!("Cannot subtract {:?} and {:?}", l, r),
+                        (SetValue(l), SetValue(r)) => Ok(SetValue(l.difference(&r))),
+                        (l, r) => {
+                            Err(Error::runtime(cannot_apply(Operation::Subtract, &l, &r))
+                                .at(operator))
+                        }
                     },
                     TokenType::Star => match (left_value, right_value) {
                         (ExpLiteralValue::Number(l), ExpLiteralValue::Number(r)) => {
                             Ok(ExpLiteralValue::Number(l * r))
                         }
-                        (l, r) => anyhow::bail!("Cannot multiply {:?} and {:?}", l, r),
+                        (SetValue(l), SetValue(r)) => Ok(SetValue(l.intersection(&r))),
+                        (l, r) => {
+                            Err(Error::runtime(cannot_apply(Operation::Multiply, &l, &r))
+                                .at(operator))
+                        }
                     },
                     TokenType::Slash => match (left_value, right_value) {
                         (ExpLiteralValue::Number(l), ExpLiteralValue::Number(r)) => {
                             if r == 0.0 {
-                                anyhow::bail!("Cannot divide by zero");
+                                return Err(Error::runtime(Message::DivisionByZero).at(operator));
                             }
                             Ok(ExpLiteralValue::Number(l / r))
                         }
-                        (l, r) => anyhow::bail!("Cannot divide {:?} and {:?}", l, r),
-                    },
-                    TokenType::Greater => match (left_value, right_value) {
-                        (ExpLiteralValue::Number(l), ExpLiteralValue::Number(r)) => {
-                            Ok(ExpLiteralValue::from_bool(l > r))
-                        }
-                        (l, r) => anyhow::bail!("Cannot compare {:?} and {:?}", l, r),
-                    },
-                    TokenType::GreaterEqual => match (left_value, right_value) {
-                        (ExpLiteralValue::Number(l), ExpLiteralValue::Number(r)) => {
-                            Ok(ExpLiteralValue::from_bool(l >= r))
+                        (l, r) => {
+                            Err(Error::runtime(cannot_apply(Operation::Divide, &l, &r))
+                                .at(operator))
                         }
-                        (l, r) => anyhow::bail!("Cannot compare {:?} and {:?}", l, r),
                     },
-                    TokenType::Less => match (left_value, right_value) {
-                        (ExpLiteralValue::Number(l), ExpLiteralValue::Number(r)) => {
-                            Ok(ExpLiteralValue::from_bool(l < r))
-                        }
-                        (l, r) => anyhow::bail!("Cannot compare {:?} and {:?}", l, r),
-                    },
-                    TokenType::LessEqual => match (left_value, right_value) {
+                    TokenType::Greater
+                    | TokenType::GreaterEqual
+                    | TokenType::Less
+                    | TokenType::LessEqual => {
+                        let result = compare(&left_value, &right_value, operator.token_type)
+                            .map_err(|error| error.or_at(operator))?;
+                        Ok(ExpLiteralValue::from_bool(result))
+                    }
+                    TokenType::DotDot | TokenType::DotDotEqual => match (left_value, right_value) {
                         (ExpLiteralValue::Number(l), ExpLiteralValue::Number(r)) => {
-                            Ok(ExpLiteralValue::from_bool(l <= r))
+                            Range::new(l, r, operator.token_type == TokenType::DotDotEqual)
+                                .map(RangeValue)
+                                .map_err(|error| error.at(operator))
                         }
-                        (l, r) => anyhow::bail!("Cannot compare {:?} and {:?}", l, r),
+                        (l, r) => Err(Error::runtime(Message::CannotMakeRange {
+                            from: l.to_string(),
+                            to: r.to_string(),
+                        })
+                        .at(operator)),
                     },
                     TokenType::EqualEqual => {
                         Ok(ExpLiteralValue::from_bool(left_value == right_value))
@@ -278,22 +548,174 @@ impl Expr {
                     TokenType::BangEqual => {
                         Ok(ExpLiteralValue::from_bool(left_value != right_value))
                     }
-                    _ => anyhow::bail!(
-                        "Cannot evaluate binary expression with operator {:?}",
-                        operator
+                    _ => Err(
+                        Error::runtime(Message::InvalidOperator(operator.lexeme.clone()))
+                            .at(operator),
                     ),
                 }
             }
+        }
+    }
+
+    // A `?.` that finds nil cuts short the whole postfix chain it starts, so
+    // `a?.b.c` is nil rather than an error when `a` is. `None` is a chain
+    // that was cut short; `evaluate` turns it into nil.
+    fn evaluate_link(&self, env: &mut Environment) -> Result<Option<ExpLiteralValue>> {
+        let (object, optional) = match self {
+            Expr::Call { callee, .. } => (callee, false),
+            Expr::Get {
+                object, optional, ..
+            }
+            | Expr::Index {
+                object, optional, ..
+            } => (object, *optional),
+            _ => return self.evaluate(env).map(Some),
+        };
+
+        match object.evaluate_link(env)? {
+            None => Ok(None),
+            Some(Nil) if optional => Ok(None),
+            Some(object) => self.apply(object, env).map(Some),
+        }
+    }
+
+    // The step of a call, property read or index, on its evaluated object.
+    fn apply(&self, object: ExpLiteralValue, env: &mut Environment) -> Result<ExpLiteralValue> {
+        match self {
+            Expr::Call {
+                paren, arguments, ..
+            } => {
+                let arguments = arguments
+                    .iter()
+                    .map(|arg| arg.evaluate(env))
+                    .collect::<Result<Vec<ExpLiteralValue>>>()?;
+
+                match object {
+                    FunctionValue(f) => f.call(arguments).or_at(paren),
+                    ConstructorValue(c) => c.construct(arguments).or_at(paren),
+                    NativeValue(n) => n.call(arguments).or_at(paren),
+                    MethodValue(m) => m.call(arguments).or_at(paren),
+                    _ => Err(Error::runtime(Message::CannotCall(object.to_string())).at(paren)),
+                }
+            }
+            Expr::Get { name, .. } => match object {
+                EnumValue(enumeration) => enumeration.variant(name).or_at(name),
+                VariantValue(variant) => variant.get(name).or_at(name),
+                GeneratorValue(generator) => generator.get(name).or_at(name),
+                SetValue(set) => set.get(name).or_at(name),
+                value => Err(Error::runtime(Message::ReadProperty {
+                    name: name.lexeme.clone(),
+                    value: value.to_string(),
+                })
+                .at(name)),
+            },
+            Expr::Index { bracket, index, .. } => {
+                let index = index.evaluate(env)?;
+                match (&object, &index) {
+                    (StringValue(s), Number(i)) => {
+                        let length = s.chars().count();
+                        if i.fract() != 0.0 || *i < 0.0 || *i as usize >= length {
+                            let message = Message::IndexOutOfRange {
+                                index: i.to_string(),
+                                collection: Collection::String,
+                                length,
+                            };
+                            return Err(Error::runtime(message).at(bracket));
+                        }
+                        let ch = s.chars().nth(*i as usize).unwrap();
+                        Ok(StringValue(ch.to_string()))
+                    }
+                    (ListValue(items), Number(i)) | (TupleValue(items), Number(i)) => {
+                        if i.fract() != 0.0 || *i < 0.0 || *i as usize >= items.len() {
+                            let message = Message::IndexOutOfRange {
+                                index: i.to_string(),
+                                collection: if matches!(object, TupleValue(_)) {
+                                    Collection::Tuple
+                                } else {
+                                    Collection::List
+                                },
+                                length: items.len(),
+                            };
+                            return Err(Error::runtime(message).at(bracket));
+                        }
+                        Ok(items[*i as usize].clone())
+                    }
+                    _ => Err(Error::runtime(Message::CannotIndex {
+                        object: object.to_string(),
+                        index: index.to_string(),
+                    })
+                    .at(bracket)),
+                }
+            }
+            _ => unreachable!("only calls, property reads and indexes are chained"),
+        }
+    }
+}
 
-            _ => todo!("Implement evaluation for {:?}", self.to_string()),
+// Numbers compare by value, tuples element by element, and sets by
+// inclusion, so `a <= b` asks whether `a` is a subset of `b`.
+fn compare(left: &ExpLiteralValue, right: &ExpLiteralValue, operator: TokenType) -> Result<bool> {
+    let ordering = match (left, right) {
+        (Number(l), Number(r)) => l.partial_cmp(r),
+        (TupleValue(l), TupleValue(r)) => {
+            let mut ordering = Some(l.len().cmp(&r.len()));
+            for (l, r) in l.iter().zip(r) {
+                if compare(l, r, TokenType::Less)? {
+                    ordering = Some(Ordering::Less);
+                    break;
+                }
+                if compare(r, l, TokenType::Less)? {
+                    ordering = Some(Ordering::Greater);
+                    break;
+                }
+            }
+            ordering
         }
+        (SetValue(l), SetValue(r)) => match (l.is_subset(r), r.is_subset(l)) {
+            (true, true) => Some(Ordering::Equal),
+            (true, false) => Some(Ordering::Less),
+            (false, true) => Some(Ordering::Greater),
+            (false, false) => None,
+        },
+        (l, r) => return Err(Error::runtime(cannot_apply(Operation::Compare, l, r))),
+    };
+
+    Ok(match (ordering, operator) {
+        (None, _) => false,
+        (Some(ordering), TokenType::Greater) => ordering.is_gt(),
+        (Some(ordering), TokenType::GreaterEqual) => ordering.is_ge(),
+        (Some(ordering), TokenType::Less) => ordering.is_lt(),
+        (Some(ordering), _) => ordering.is_le(),
+    })
+}
+
+fn cannot_apply(operation: Operation, left: &ExpLiteralValue, right: &ExpLiteralValue) -> Message {
+    Message::CannotApply {
+        operation,
+        left: left.to_string(),
+        right: right.to_string(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lexer::Token;
+    use crate::lexer::{Lexer, Token};
+    use crate::native;
+    use crate::parser::Parser;
+
+    fn run(source: &str) -> Result<Environment> {
+        let tokens = Lexer::new(source).lex()?;
+        let statements = Parser::new(tokens).parse()?;
+
+        let mut env = Environment::new();
+        native::define_globals(&mut env);
+        for statement in statements {
+            statement.execute(&mut env)?;
+        }
+
+        Ok(env)
+    }
 
     #[test]
     fn test_evaluation() {
@@ -306,6 +728,9 @@ mod tests {
                 lexeme: "+".to_string(),
                 literal: None,
                 line_number: 0,
+                column: 0,
+                offset: 0,
+                doc: None,
             },
             right: Box::new(Expr::Literal {
                 value: ExpLiteralValue::Number(2.0),
@@ -326,6 +751,9 @@ mod tests {
                 lexeme: "-".to_string(),
                 literal: None,
                 line_number: 0,
+                column: 0,
+                offset: 0,
+                doc: None,
             },
             right: Box::new(Expr::Literal {
                 value: ExpLiteralValue::Number(1.0),
@@ -349,6 +777,9 @@ mod tests {
                 lexeme: ">".to_string(),
                 literal: None,
                 line_number: 0,
+                column: 0,
+                offset: 0,
+                doc: None,
             },
             right: Box::new(Expr::Literal {
                 value: ExpLiteralValue::Number(2.0),
@@ -373,6 +804,9 @@ mod tests {
                 lexeme: "+".to_string(),
                 literal: None,
                 line_number: 0,
+                column: 0,
+                offset: 0,
+                doc: None,
             },
             right: Box::new(Expr::Grouping {
                 expression: Box::new(Expr::Binary {
@@ -384,6 +818,9 @@ mod tests {
                         lexeme: "*".to_string(),
                         literal: None,
                         line_number: 0,
+                        column: 0,
+                        offset: 0,
+                        doc: None,
                     },
                     right: Box::new(Expr::Literal {
                         value: ExpLiteralValue::Number(3.0),
@@ -400,4 +837,435 @@ mod tests {
 
     #[test]
     fn test_assignment() {}
+
+    #[test]
+    fn test_enum_variants() {
+        let env = run("nooc Xaalad { Sugan, Diray(taariikh), Gaadhay }
+             weel a = Xaalad.Sugan;
+             weel b = Xaalad.Diray(12);
+             weel c = Xaalad.Diray(12).taariikh;
+             weel d = Xaalad.Sugan == Xaalad.Sugan;
+             weel e = Xaalad.Diray(1) == Xaalad.Diray(2);")
+        .unwrap();
+
+        assert_eq!(env.get("a").unwrap().to_string(), "Xaalad.Sugan");
+        assert_eq!(env.get("b").unwrap().to_string(), "Xaalad.Diray(12)");
+        assert_eq!(env.get("c"), Some(&ExpLiteralValue::Number(12.0)));
+        assert_eq!(env.get("d"), Some(&ExpLiteralValue::True));
+        assert_eq!(env.get("e"), Some(&ExpLiteralValue::False));
+    }
+
+    #[test]
+    fn test_enum_undefined_variant() {
+        let error = run("nooc Xaalad { Sugan, Gaadhay }
+             weel a = Xaalad.Lumay;")
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Undefined variant 'Lumay' for enum 'Xaalad'. Valid variants are: Sugan, Gaadhay"
+        );
+    }
+
+    #[test]
+    fn test_match_on_enum_variants() {
+        let env = run("nooc Xaalad { Sugan, Diray(taariikh, meel), Gaadhay }
+             hawl sharax(x) {
+                 dooro x {
+                     Xaalad.Sugan { celi \"sugan\"; }
+                     Xaalad.Diray(t, m) { celi [m, t]; }
+                     kale { celi \"wax kale\"; }
+                 }
+             }
+             weel a = sharax(Xaalad.Sugan);
+             weel b = sharax(Xaalad.Diray(12, \"Hargeysa\"));
+             weel c = sharax(Xaalad.Gaadhay);
+             weel d = sharax(5);
+             weel e = 0;
+             dooro Xaalad.Gaadhay { Xaalad.Gaadhay { e = 1; } }")
+        .unwrap();
+
+        assert_eq!(env.get("a").unwrap().to_string(), "sugan");
+        assert_eq!(env.get("b").unwrap().to_string(), "[Hargeysa, 12]");
+        assert_eq!(env.get("c").unwrap().to_string(), "wax kale");
+        assert_eq!(env.get("d").unwrap().to_string(), "wax kale");
+        assert_eq!(env.get("e"), Some(&ExpLiteralValue::Number(1.0)));
+        assert_eq!(env.get("t"), None);
+    }
+
+    #[test]
+    fn test_match_errors() {
+        let error = |source: &str| {
+            run(&format!(
+                "nooc Xaalad {{ Sugan, Diray(taariikh) }} {}",
+                source
+            ))
+            .unwrap_err()
+            .to_string()
+        };
+
+        assert_eq!(
+            error("dooro Xaalad.Diray(1) { Xaalad.Sugan {} }"),
+            "No arm of 'dooro' matches Xaalad.Diray(1)"
+        );
+        assert_eq!(
+            error("dooro Xaalad.Sugan { Xaalad.Lumay {} }"),
+            "Undefined variant 'Lumay' for enum 'Xaalad'. Valid variants are: Sugan, Diray"
+        );
+        assert_eq!(
+            error("dooro Xaalad.Sugan { Xaalad.Diray {} }"),
+            "Variant 'Xaalad.Diray' expects 1 arguments but got 0"
+        );
+    }
+
+    #[test]
+    fn test_conditional_and_nil_operators() {
+        let env = run("nooc Xaalad { Diray(taariikh) }
+             weel x = waxba;
+             weel a = run ? 1 : 2;
+             weel b = been ? 1 : 2;
+             weel c = x ?? 5;
+             weel d = 0 ?? 5;
+             weel e = x?.taariikh;
+             weel f = x?.[0];
+             weel g = Xaalad.Diray(3)?.taariikh;
+             weel h = \"salaan\"?.[1];")
+        .unwrap();
+
+        assert_eq!(env.get("a"), Some(&ExpLiteralValue::Number(1.0)));
+        assert_eq!(env.get("b"), Some(&ExpLiteralValue::Number(2.0)));
+        assert_eq!(env.get("c"), Some(&ExpLiteralValue::Number(5.0)));
+        assert_eq!(env.get("d"), Some(&ExpLiteralValue::Number(0.0)));
+        assert_eq!(env.get("e"), Some(&ExpLiteralValue::Nil));
+        assert_eq!(env.get("f"), Some(&ExpLiteralValue::Nil));
+        assert_eq!(env.get("g"), Some(&ExpLiteralValue::Number(3.0)));
+        assert_eq!(
+            env.get("h"),
+            Some(&ExpLiteralValue::StringValue("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_logical_operators_short_circuit() {
+        let env = run("weel a = run ama been;
+             weel b = been ama \"kale\";
+             weel c = 1 iyo 2;
+             weel d = 0 iyo 2;
+             weel e = waxba ama been iyo run;
+             weel f = been iyo xaqiiq();
+             weel g = run ama xaqiiq();")
+        .unwrap();
+
+        assert_eq!(env.get("a"), Some(&ExpLiteralValue::True));
+        assert_eq!(
+            env.get("b"),
+            Some(&ExpLiteralValue::StringValue("kale".to_string()))
+        );
+        assert_eq!(env.get("c"), Some(&ExpLiteralValue::Number(2.0)));
+        assert_eq!(env.get("d"), Some(&ExpLiteralValue::Number(0.0)));
+        assert_eq!(env.get("e"), Some(&ExpLiteralValue::False));
+        assert_eq!(env.get("f"), Some(&ExpLiteralValue::False));
+        assert_eq!(env.get("g"), Some(&ExpLiteralValue::True));
+    }
+
+    #[test]
+    fn test_optional_chaining_short_circuits_the_chain() {
+        let env = run("weel x = waxba;
+             weel a = x?.a.b;
+             weel b = x?.[0].c[1](2);
+             weel c = x?.a?.b.c;
+             weel d = [[1, 2]]?.[0][1];")
+        .unwrap();
+
+        assert_eq!(env.get("a"), Some(&ExpLiteralValue::Nil));
+        assert_eq!(env.get("b"), Some(&ExpLiteralValue::Nil));
+        assert_eq!(env.get("c"), Some(&ExpLiteralValue::Nil));
+        assert_eq!(env.get("d"), Some(&ExpLiteralValue::Number(2.0)));
+
+        let error = run("weel x = waxba; weel y = (x?.a).b;").unwrap_err();
+        assert_eq!(error.to_string(), "Cannot read property 'b' of nil");
+    }
+
+    #[test]
+    fn test_property_of_nil_without_optional_chaining() {
+        let error = run("weel x = waxba; weel y = x.magac;").unwrap_err();
+
+        assert_eq!(error.to_string(), "Cannot read property 'magac' of nil");
+    }
+
+    #[test]
+    fn test_ranges() {
+        let env = run("weel a = liis(1..4);
+             weel b = liis(1..=4);
+             weel c = dherer(0..16777216);
+             weel d = liis(3..1);")
+        .unwrap();
+
+        assert_eq!(env.get("a").unwrap().to_string(), "[1, 2, 3]");
+        assert_eq!(env.get("b").unwrap().to_string(), "[1, 2, 3, 4]");
+        assert_eq!(env.get("c"), Some(&ExpLiteralValue::Number(16777216.0)));
+        assert_eq!(env.get("d").unwrap().to_string(), "[]");
+    }
+
+    #[test]
+    fn test_range_too_large() {
+        let cases = [
+            (
+                "daabac dherer(0..=1e30);",
+                "0",
+                "1000000000000000000000000000000",
+            ),
+            (
+                "markasta x ku 0..=1e30 {}",
+                "0",
+                "1000000000000000000000000000000",
+            ),
+            ("daabac wadar(16777200..16777300);", "16777200", "16777300"),
+            ("daabac dherer(-16777218..0);", "-16777218", "0"),
+        ];
+
+        for (source, from, to) in cases {
+            assert_eq!(
+                run(source).unwrap_err().to_string(),
+                format!(
+                    "Range from {} to {} is too large; bounds must lie between -16777216 and 16777216",
+                    from, to
+                ),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn test_for_each_over_iterables() {
+        let env = run("weel wadarta = 0;
+             markasta i ku 1..=10 { wadarta = wadarta + i; }
+             weel xarfo = \"\";
+             markasta x ku \"abc\" { xarfo = x + xarfo; }
+             weel tirooyin = 0;
+             markasta n ku [4, 5, 6] { tirooyin = tirooyin + n; }
+             weel s = wadar(1..=10);")
+        .unwrap();
+
+        assert_eq!(env.get("wadarta"), Some(&ExpLiteralValue::Number(55.0)));
+        assert_eq!(
+            env.get("xarfo"),
+            Some(&ExpLiteralValue::StringValue("cba".to_string()))
+        );
+        assert_eq!(env.get("tirooyin"), Some(&ExpLiteralValue::Number(15.0)));
+        assert_eq!(env.get("s"), Some(&ExpLiteralValue::Number(55.0)));
+    }
+
+    #[test]
+    fn test_for_each_over_non_iterable() {
+        let error = run("markasta x ku 5 { daabac x; }").unwrap_err();
+
+        assert_eq!(error.to_string(), "5 is not iterable");
+    }
+
+    #[test]
+    fn test_operand_errors_show_values() {
+        let cases = [
+            ("weel x = -\"a\";", "Cannot negate a"),
+            ("weel x = [1] + 2;", "Cannot add [1] and 2"),
+            ("weel x = \"a\"..2;", "Cannot make a range from a to 2"),
+            ("weel x = 5(1);", "Cannot call 5"),
+        ];
+
+        for (source, message) in cases {
+            assert_eq!(run(source).unwrap_err().to_string(), message, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_return_value() {
+        let env = run("hawl labanlaab(n) { celi n * 2; daabac \"lama gaaro\"; }
+             weel a = labanlaab(21);")
+        .unwrap();
+
+        assert_eq!(env.get("a"), Some(&ExpLiteralValue::Number(42.0)));
+    }
+
+    #[test]
+    fn test_generators() {
+        let env = run("hawl dhaban(n) {
+                 markasta i ku 0..n { bixi i * 2; }
+                 celi;
+                 bixi 100;
+             }
+             weel g = dhaban(3);
+             weel a = g.xiga();
+             weel b = liis(g);
+             weel c = g.xiga();
+             weel wadarta = 0;
+             markasta x ku dhaban(4) { wadarta = wadarta + x; }")
+        .unwrap();
+
+        assert_eq!(env.get("a"), Some(&ExpLiteralValue::Number(0.0)));
+        assert_eq!(env.get("b").unwrap().to_string(), "[2, 4]");
+        assert_eq!(env.get("c"), Some(&ExpLiteralValue::Nil));
+        assert_eq!(env.get("wadarta"), Some(&ExpLiteralValue::Number(12.0)));
+    }
+
+    #[test]
+    fn test_yield_outside_function() {
+        let error = run("{ bixi 1; }").unwrap_err();
+
+        assert_eq!(error.to_string(), "Cannot yield outside of a function");
+    }
+
+    #[test]
+    fn test_assign_to_constant_at_run_time() {
+        let error = run(
+            "joogto magac = \"Ahmed\";\nhawl beddel() { magac = \"Cali\"; }\nweel r = beddel();",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Cannot assign to constant 'magac' declared on line 1"
+        );
+    }
+
+    #[test]
+    fn test_destructuring_declarations() {
+        let env = run("nooc Qof { Xubin(magac, da) }
+             weel [a, b, ...rest] = [1, 2, 3, 4];
+             weel [c, d = 7] = 5..6;
+             weel [[e, f], ...g] = [[8, 9]];
+             weel { magac, da, xaafad = \"Hodan\" } = Qof.Xubin(\"Cali\", 30);")
+        .unwrap();
+
+        assert_eq!(env.get("a"), Some(&ExpLiteralValue::Number(1.0)));
+        assert_eq!(env.get("b"), Some(&ExpLiteralValue::Number(2.0)));
+        assert_eq!(env.get("rest").unwrap().to_string(), "[3, 4]");
+        assert_eq!(env.get("c"), Some(&ExpLiteralValue::Number(5.0)));
+        assert_eq!(env.get("d"), Some(&ExpLiteralValue::Number(7.0)));
+        assert_eq!(env.get("f"), Some(&ExpLiteralValue::Number(9.0)));
+        assert_eq!(env.get("g").unwrap().to_string(), "[]");
+        assert_eq!(env.get("magac").unwrap().to_string(), "Cali");
+        assert_eq!(env.get("da"), Some(&ExpLiteralValue::Number(30.0)));
+        assert_eq!(env.get("xaafad").unwrap().to_string(), "Hodan");
+    }
+
+    #[test]
+    fn test_destructuring_parameters() {
+        let env = run("hawl f([a, b], c = 10) { celi [b, a, c]; }
+             weel x = f([1, 2]);
+             weel y = f(\"ab\", 3);")
+        .unwrap();
+
+        assert_eq!(env.get("x").unwrap().to_string(), "[2, 1, 10]");
+        assert_eq!(env.get("y").unwrap().to_string(), "[b, a, 3]");
+
+        let error = run("hawl f(a, b = 1) { celi a; }\nweel x = f();").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Function 'f' expects at least 1 arguments but got 0"
+        );
+    }
+
+    #[test]
+    fn test_destructuring_shape_mismatch() {
+        let cases = [
+            (
+                "weel [a, b, c] = [1, 2];",
+                "List pattern expects at least 3 elements but got 2",
+            ),
+            (
+                "weel [a] = [1, 2];",
+                "List pattern expects at most 1 elements but got more",
+            ),
+            (
+                "weel [a, b] = 5;",
+                "Cannot destructure 5 with a list pattern",
+            ),
+            (
+                "weel { magac } = [1];",
+                "Cannot destructure [1] with an object pattern",
+            ),
+            (
+                "nooc Qof { Xubin(da) }\nweel { magac } = Qof.Xubin(3);",
+                "Qof.Xubin(3) has no field 'magac' to destructure",
+            ),
+            ("weel [a, b];", "Expected '=' after destructuring pattern"),
+        ];
+
+        for (source, message) in cases {
+            assert_eq!(run(source).unwrap_err().to_string(), message);
+        }
+    }
+
+    #[test]
+    fn test_tuples() {
+        let env = run("hawl qeybi(a, b) { weel t = (a / b, a - b); celi t; }
+             weel t = qeybi(8, 2);
+             weel [q, r] = t;
+             weel a = t[1];
+             weel b = (1, \"x\") == (1, \"x\");
+             weel c = (1, 2) < (1, 3);
+             weel d = (2,);
+             weel e = ();
+             weel f = dherer(t);
+             weel g = (12,);
+             weel h = (\"ab\",);")
+        .unwrap();
+
+        assert_eq!(env.get("t").unwrap().to_string(), "(4, 6)");
+        assert_eq!(env.get("q"), Some(&ExpLiteralValue::Number(4.0)));
+        assert_eq!(env.get("r"), Some(&ExpLiteralValue::Number(6.0)));
+        assert_eq!(env.get("a"), Some(&ExpLiteralValue::Number(6.0)));
+        assert_eq!(env.get("b"), Some(&ExpLiteralValue::True));
+        assert_eq!(env.get("c"), Some(&ExpLiteralValue::True));
+        assert_eq!(env.get("d").unwrap().to_string(), "(2,)");
+        assert_eq!(env.get("e").unwrap().to_string(), "()");
+        assert_eq!(env.get("f"), Some(&ExpLiteralValue::Number(2.0)));
+        assert_eq!(env.get("g").unwrap().to_string(), "(12,)");
+        assert_eq!(env.get("h").unwrap().to_string(), "(ab,)");
+    }
+
+    #[test]
+    fn test_sets() {
+        let env = run("weel a = urur([3, 1, 2, 1]);
+             weel b = urur(2..5);
+             weel c = a + b;
+             weel d = a * b;
+             weel e = a - b;
+             weel f = a.leeyahay(3);
+             weel g = a.leeyahay(4);
+             weel h = urur([(1, 2), (1, 2), (0, 5)]);
+             weel i = urur([2, 1]) <= a;
+             weel j = a == urur([1, 2, 3]);")
+        .unwrap();
+
+        assert_eq!(env.get("a").unwrap().to_string(), "{1, 2, 3}");
+        assert_eq!(env.get("c").unwrap().to_string(), "{1, 2, 3, 4}");
+        assert_eq!(env.get("d").unwrap().to_string(), "{2, 3}");
+        assert_eq!(env.get("e").unwrap().to_string(), "{1}");
+        assert_eq!(env.get("f"), Some(&ExpLiteralValue::True));
+        assert_eq!(env.get("g"), Some(&ExpLiteralValue::False));
+        assert_eq!(env.get("h").unwrap().to_string(), "{(0, 5), (1, 2)}");
+        assert_eq!(env.get("i"), Some(&ExpLiteralValue::True));
+        assert_eq!(env.get("j"), Some(&ExpLiteralValue::True));
+
+        let error = run("hawl f() {}\nweel s = urur([f]);").unwrap_err();
+        assert_eq!(error.to_string(), "<hawl f> cannot be a set member");
+    }
+
+    #[test]
+    fn test_equal_values_hash_equally() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |value: &ExpLiteralValue| {
+            let mut hasher = DefaultHasher::new();
+            value.hash(&mut hasher);
+            hasher.finish()
+        };
+        let tuple = |x: f32| TupleValue(vec![Number(x), StringValue("x".to_string())]);
+
+        assert_eq!(tuple(0.0), tuple(-0.0));
+        assert_eq!(hash(&tuple(0.0)), hash(&tuple(-0.0)));
+        assert_eq!(tuple(1.0).total_cmp(&tuple(-0.0)), Ordering::Greater);
+    }
 }
@@ -0,0 +1,115 @@
+use std::{cell::RefCell, rc::Rc};
+
+use crate::{
+    error::{Error, Result},
+    expr::ExpLiteralValue,
+    iterator::ValueIterator,
+    lexer::Token,
+    message::Message,
+    native::{BoundMethod, NativeFunction},
+    routine::{Completion, Routine},
+};
+
+const METHODS: &[NativeFunction] = &[NativeFunction::new("xiga", 1, next)];
+
+#[derive(Debug)]
+struct GeneratorState {
+    routine: Routine,
+    done: bool,
+}
+
+// Copies of a generator share their state, so advancing one copy advances
+// every other copy too.
+#[derive(Debug, Clone)]
+pub struct Generator {
+    name: String,
+    state: Rc<RefCell<GeneratorState>>,
+}
+
+impl PartialEq for Generator {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl PartialOrd for Generator {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        if self == other {
+            Some(std::cmp::Ordering::Equal)
+        } else {
+            None
+        }
+    }
+}
+
+impl Generator {
+    pub fn new(name: &str, routine: Routine) -> Self {
+        Self {
+            name: name.to_string(),
+            state: Rc::new(RefCell::new(GeneratorState {
+                routine,
+                done: false,
+            })),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Runs the generator body up to its next `bixi` and returns the yielded
+    /// value, or `None` once the body has finished.
+    pub fn resume(&self) -> Result<Option<ExpLiteralValue>> {
+        let mut state = match self.state.try_borrow_mut() {
+            Ok(state) => state,
+            Err(_) => return Err(Error::runtime(Message::GeneratorRunning(self.name.clone()))),
+        };
+
+        if state.done {
+            return Ok(None);
+        }
+
+        match state.routine.resume() {
+            Ok(Completion::Yielded(value)) => Ok(Some(value)),
+            Ok(Completion::Returned(_)) | Ok(Completion::Finished) => {
+                state.done = true;
+                Ok(None)
+            }
+            Ok(Completion::Blocked) | Ok(Completion::Spawned { .. }) => {
+                state.done = true;
+                Err(Error::runtime(Message::NestedTask))
+            }
+            Err(e) => {
+                state.done = true;
+                Err(e)
+            }
+        }
+    }
+
+    pub fn iter(&self) -> ValueIterator {
+        let generator = self.clone();
+        Box::new(std::iter::from_fn(move || generator.resume().transpose()))
+    }
+
+    pub fn get(&self, name: &Token) -> Result<ExpLiteralValue> {
+        match METHODS.iter().find(|method| method.name() == name.lexeme) {
+            Some(method) => Ok(ExpLiteralValue::MethodValue(BoundMethod::new(
+                ExpLiteralValue::GeneratorValue(self.clone()),
+                method.clone(),
+            ))),
+            None => Err(Error::runtime(Message::GeneratorProperty {
+                generator: self.name.clone(),
+                name: name.lexeme.clone(),
+            })),
+        }
+    }
+}
+
+fn next(args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+    match &args[0] {
+        ExpLiteralValue::GeneratorValue(generator) => {
+            Ok(generator.resume()?.unwrap_or(ExpLiteralValue::Nil))
+        }
+        value => Err(Error::runtime(Message::CannotCallNext(value.to_string()))),
+    }
+}
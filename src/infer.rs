@@ -0,0 +1,864 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use crate::{
+    error::{Error, Errors},
+    expr::{ExpLiteralValue, Expr},
+    lexer::{Token, TokenType},
+    message::{Message, Operation},
+    pattern::Pattern,
+    statement::Stmt,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Var(usize),
+    Number,
+    String,
+    Bool,
+    Nil,
+    Range,
+    List(Box<Type>),
+    Set(Box<Type>),
+    Tuple(Vec<Type>),
+    Function(Vec<Type>, Box<Type>),
+    // Values the pass cannot describe, such as enums, generators and
+    // channels. Unifies with anything without learning from it.
+    Dynamic,
+}
+
+#[derive(Debug, Clone)]
+struct Scheme {
+    vars: Vec<usize>,
+    body: Type,
+}
+
+impl Scheme {
+    fn mono(body: Type) -> Self {
+        Self {
+            vars: Vec::new(),
+            body,
+        }
+    }
+}
+
+// Hindley-Milner inference over the whole program. Functions declared with
+// `hawl` and constants are generalised, so `hawl aqoonsi(x) { celi x; }`
+// can be used at several types; `weel` bindings can be reassigned and stay
+// monomorphic. Arithmetic on a type variable that is never pinned down is
+// left polymorphic rather than rejected.
+pub struct Inference {
+    bindings: Vec<Option<Type>>,
+    scopes: Vec<HashMap<String, Scheme>>,
+    globals: Vec<String>,
+    returns: Vec<(String, Type)>,
+    operators: Vec<(Token, Type, &'static [&'static str])>,
+    errors: Vec<Error>,
+}
+
+const ADDABLE: &[&str] = &["Tiro", "Qoraal", "Urur"];
+const ARITHMETIC: &[&str] = &["Tiro", "Urur"];
+const DIVISIBLE: &[&str] = &["Tiro"];
+const COMPARABLE: &[&str] = &["Tiro", "Koox", "Urur"];
+
+impl Inference {
+    pub fn new() -> Self {
+        let mut inference = Self {
+            bindings: Vec::new(),
+            scopes: vec![HashMap::new()],
+            globals: Vec::new(),
+            returns: Vec::new(),
+            operators: Vec::new(),
+            errors: Vec::new(),
+        };
+
+        let natives = [
+            ("dherer", vec![Type::Dynamic], Type::Number),
+            ("kanaal", vec![Type::Number], Type::Dynamic),
+            (
+                "liis",
+                vec![Type::Dynamic],
+                Type::List(Box::new(Type::Dynamic)),
+            ),
+            (
+                "urur",
+                vec![Type::Dynamic],
+                Type::Set(Box::new(Type::Dynamic)),
+            ),
+            ("wadar", vec![Type::Dynamic], Type::Number),
+        ];
+        for (name, params, returns) in natives {
+            inference.scopes[0].insert(
+                name.to_string(),
+                Scheme::mono(Type::Function(params, Box::new(returns))),
+            );
+        }
+
+        inference
+    }
+
+    /// Infers types for the whole program and returns `name: type` for each
+    /// top-level binding, in declaration order.
+    pub fn infer(&mut self, statements: &[Stmt]) -> Result<Vec<String>> {
+        for statement in statements {
+            self.infer_statement(statement);
+        }
+        self.check_operators();
+
+        if !self.errors.is_empty() {
+            let errors = std::mem::take(&mut self.errors);
+            anyhow::bail!(Errors(errors));
+        }
+
+        Ok(self
+            .globals
+            .iter()
+            .map(|name| {
+                let scheme = &self.scopes[0][name];
+                format!("{}: {}", name, self.display(scheme))
+            })
+            .collect())
+    }
+
+    fn infer_statement(&mut self, statement: &Stmt) {
+        match statement {
+            Stmt::Expression { expression } | Stmt::Print { expression } => {
+                self.infer_expression(expression);
+            }
+            Stmt::Var {
+                pattern,
+                annotation,
+                initializer,
+                ..
+            } => {
+                let value = match initializer {
+                    Some(initializer) => self.infer_expression(initializer),
+                    None => Type::Nil,
+                };
+                if let Some(annotation) = annotation {
+                    let declared = annotated(annotation);
+                    if !self.unify(&declared, &value) {
+                        let (expected, actual) = (self.show(&declared), self.show(&value));
+                        self.error(annotation, Message::AnnotationType { expected, actual });
+                    }
+                }
+                self.bind_pattern(pattern, value);
+            }
+            Stmt::Const { name, initializer } => {
+                let value = self.infer_expression(initializer);
+                let scheme = self.generalize(&value);
+                self.declare(name, scheme);
+            }
+            Stmt::Fun {
+                name,
+                params,
+                return_type,
+                body,
+                ..
+            } => {
+                let function = self.fresh();
+                self.declare(name, Scheme::mono(function.clone()));
+
+                self.scopes.push(HashMap::new());
+                let mut params_types = Vec::new();
+                for param in params {
+                    let param_type = self.fresh();
+                    if let Some(annotation) = &param.annotation {
+                        self.unify(&param_type, &annotated(annotation));
+                    }
+                    if let Some(default) = &param.default {
+                        let default_type = self.infer_expression(default);
+                        if !self.unify(&param_type, &default_type) {
+                            let at = param.pattern.names().first().copied().unwrap_or(name);
+                            let (expected, actual) =
+                                (self.show(&param_type), self.show(&default_type));
+                            self.error(
+                                at,
+                                Message::DefaultType {
+                                    parameter: at.lexeme.clone(),
+                                    function: name.lexeme.clone(),
+                                    expected,
+                                    actual,
+                                },
+                            );
+                        }
+                    }
+                    self.bind_pattern(&param.pattern, param_type.clone());
+                    params_types.push(param_type);
+                }
+
+                let returns = if body.iter().any(Stmt::contains_yield) {
+                    Type::Dynamic
+                } else if body.iter().any(returns_value) {
+                    self.fresh()
+                } else {
+                    Type::Nil
+                };
+                if let Some(annotation) = return_type {
+                    self.unify(&returns, &annotated(annotation));
+                }
+
+                self.returns.push((name.lexeme.clone(), returns.clone()));
+                for statement in body {
+                    self.infer_statement(statement);
+                }
+                self.returns.pop();
+                self.scopes.pop();
+
+                let signature = Type::Function(params_types, Box::new(returns));
+                self.unify(&function, &signature);
+
+                // Take the function's own name out of the environment before
+                // generalising, so its variables are not considered free.
+                self.scopes.last_mut().unwrap().remove(&name.lexeme);
+                let scheme = self.generalize(&signature);
+                self.declare(name, scheme);
+            }
+            Stmt::Enum { name, .. } => self.declare(name, Scheme::mono(Type::Dynamic)),
+            Stmt::Block { statements } => {
+                self.scopes.push(HashMap::new());
+                for statement in statements {
+                    self.infer_statement(statement);
+                }
+                self.scopes.pop();
+            }
+            Stmt::ForEach {
+                name,
+                iterable,
+                body,
+            } => {
+                let iterable = self.infer_expression(iterable);
+                let item = match self.resolve(&iterable) {
+                    Type::List(item) | Type::Set(item) => *item,
+                    Type::String => Type::String,
+                    Type::Range => Type::Number,
+                    _ => Type::Dynamic,
+                };
+
+                self.scopes.push(HashMap::new());
+                self.declare(name, Scheme::mono(item));
+                for statement in body {
+                    self.infer_statement(statement);
+                }
+                self.scopes.pop();
+            }
+            Stmt::Match { subject, arms, .. } => {
+                self.infer_expression(subject);
+                for arm in arms {
+                    self.scopes.push(HashMap::new());
+                    for binding in arm.case.bindings() {
+                        self.declare(binding, Scheme::mono(Type::Dynamic));
+                    }
+                    for statement in &arm.body {
+                        self.infer_statement(statement);
+                    }
+                    self.scopes.pop();
+                }
+            }
+            Stmt::Return { keyword, value } => {
+                let value = match value {
+                    Some(value) => self.infer_expression(value),
+                    None => Type::Nil,
+                };
+                if let Some((function, returns)) = self.returns.last().cloned() {
+                    if !self.unify(&returns, &value) {
+                        let (expected, actual) = (self.show(&returns), self.show(&value));
+                        self.error(
+                            keyword,
+                            Message::ReturnType {
+                                function,
+                                expected,
+                                actual,
+                            },
+                        );
+                    }
+                }
+            }
+            Stmt::Yield { value, .. } | Stmt::Spawn { call: value, .. } => {
+                self.infer_expression(value);
+            }
+            Stmt::Send { value, channel, .. } => {
+                self.infer_expression(value);
+                self.infer_expression(channel);
+            }
+            Stmt::Receive { name, channel, .. } => {
+                self.infer_expression(channel);
+                self.declare(name, Scheme::mono(Type::Dynamic));
+            }
+        }
+    }
+
+    fn infer_expression(&mut self, expression: &Expr) -> Type {
+        match expression {
+            Expr::Literal { value } => match value {
+                ExpLiteralValue::Number(_) => Type::Number,
+                ExpLiteralValue::StringValue(_) => Type::String,
+                ExpLiteralValue::True | ExpLiteralValue::False => Type::Bool,
+                ExpLiteralValue::Nil => Type::Nil,
+                _ => Type::Dynamic,
+            },
+            Expr::Variable { name } => match self.lookup(name) {
+                Some(scheme) => self.instantiate(&scheme),
+                None => Type::Dynamic,
+            },
+            Expr::Grouping { expression } => self.infer_expression(expression),
+            Expr::Assignment { name, value } => {
+                let value = self.infer_expression(value);
+                if let Some(scheme) = self.lookup(name) {
+                    let declared = self.instantiate(&scheme);
+                    if !self.unify(&declared, &value) {
+                        let (declared, actual) = (self.show(&declared), self.show(&value));
+                        self.error(
+                            name,
+                            Message::AssignmentType {
+                                name: name.lexeme.clone(),
+                                declared,
+                                actual,
+                            },
+                        );
+                    }
+                }
+                value
+            }
+            Expr::Unary { operator, right } => {
+                let right = self.infer_expression(right);
+                match operator.token_type {
+                    TokenType::Bang => Type::Bool,
+                    _ => {
+                        if !self.unify(&Type::Number, &right) {
+                            let right = self.show(&right);
+                            self.error(operator, Message::CannotNegate(right));
+                        }
+                        Type::Number
+                    }
+                }
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.infer_expression(left);
+                let right = self.infer_expression(right);
+                self.infer_binary(operator, left, right)
+            }
+            Expr::Logical {
+                left,
+                operator,
+                right,
+            } => {
+                let left = self.infer_expression(left);
+                let right = self.infer_expression(right);
+                match self.resolve(&left) {
+                    Type::Nil if operator.token_type != TokenType::And => right,
+                    _ if self.unify(&left, &right) => left,
+                    _ => Type::Dynamic,
+                }
+            }
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.infer_expression(condition);
+                let then_type = self.infer_expression(then_branch);
+                let else_type = self.infer_expression(else_branch);
+                if self.unify(&then_type, &else_type) {
+                    then_type
+                } else {
+                    Type::Dynamic
+                }
+            }
+            Expr::Call {
+                callee,
+                paren,
+                arguments,
+            } => {
+                let function = match callee.as_ref() {
+                    Expr::Variable { name } => name.lexeme.clone(),
+                    _ => "function".to_string(),
+                };
+                let callee = self.infer_expression(callee);
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| self.infer_expression(argument))
+                    .collect::<Vec<Type>>();
+
+                match self.resolve(&callee) {
+                    Type::Function(params, returns) => {
+                        if arguments.len() > params.len() {
+                            self.error(
+                                paren,
+                                Message::TooManyArguments {
+                                    name: function.clone(),
+                                    expected: params.len(),
+                                    got: arguments.len(),
+                                },
+                            );
+                        }
+                        for (index, (param, argument)) in params.iter().zip(&arguments).enumerate()
+                        {
+                            if !self.unify(param, argument) {
+                                let (expected, actual) = (self.show(param), self.show(argument));
+                                self.error(
+                                    paren,
+                                    Message::ArgumentType {
+                                        index: index + 1,
+                                        function: function.clone(),
+                                        expected,
+                                        actual,
+                                    },
+                                );
+                            }
+                        }
+                        *returns
+                    }
+                    Type::Var(_) => {
+                        let returns = self.fresh();
+                        let function = Type::Function(arguments, Box::new(returns.clone()));
+                        self.unify(&callee, &function);
+                        returns
+                    }
+                    Type::Dynamic => Type::Dynamic,
+                    other => {
+                        let other = self.show(&other);
+                        self.error(paren, Message::CannotCall(other));
+                        Type::Dynamic
+                    }
+                }
+            }
+            Expr::Get { object, .. } => {
+                self.infer_expression(object);
+                Type::Dynamic
+            }
+            Expr::Index { object, index, .. } => {
+                let object = self.infer_expression(object);
+                self.infer_expression(index);
+                match self.resolve(&object) {
+                    Type::String => Type::String,
+                    Type::List(item) => *item,
+                    _ => Type::Dynamic,
+                }
+            }
+            Expr::List { elements } => {
+                let item = self.fresh();
+                for element in elements {
+                    let element = self.infer_expression(element);
+                    if !self.unify(&item, &element) {
+                        return Type::List(Box::new(Type::Dynamic));
+                    }
+                }
+                Type::List(Box::new(item))
+            }
+            Expr::Tuple { elements } => Type::Tuple(
+                elements
+                    .iter()
+                    .map(|element| self.infer_expression(element))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn infer_binary(&mut self, operator: &Token, left: Type, right: Type) -> Type {
+        let (operation, allowed) = match operator.token_type {
+            TokenType::EqualEqual | TokenType::BangEqual => return Type::Bool,
+            TokenType::DotDot | TokenType::DotDotEqual => {
+                if !self.unify(&left, &Type::Number) || !self.unify(&right, &Type::Number) {
+                    let (from, to) = (self.show(&left), self.show(&right));
+                    self.error(operator, Message::CannotMakeRange { from, to });
+                }
+                return Type::Range;
+            }
+            TokenType::Plus => (Operation::Add, ADDABLE),
+            TokenType::Minus => (Operation::Subtract, ARITHMETIC),
+            TokenType::Star => (Operation::Multiply, ARITHMETIC),
+            TokenType::Slash => (Operation::Divide, DIVISIBLE),
+            _ => (Operation::Compare, COMPARABLE),
+        };
+
+        if !self.unify(&left, &right) {
+            let (left, right) = (self.show(&left), self.show(&right));
+            self.error(
+                operator,
+                Message::CannotApply {
+                    operation,
+                    left,
+                    right,
+                },
+            );
+            return Type::Dynamic;
+        }
+        self.operators
+            .push((operator.clone(), left.clone(), allowed));
+
+        match operation {
+            Operation::Compare => Type::Bool,
+            _ => left,
+        }
+    }
+
+    // Operands are only checked once the whole program has been seen, since
+    // a later use may be what pins their type down.
+    fn check_operators(&mut self) {
+        for (operator, operand, allowed) in std::mem::take(&mut self.operators) {
+            let operand = self.resolve(&operand);
+            let accepted = match &operand {
+                Type::Var(_) | Type::Dynamic => true,
+                operand => allowed.contains(&kind(operand)),
+            };
+            if !accepted {
+                let operation = match operator.token_type {
+                    TokenType::Plus => Operation::Add,
+                    TokenType::Minus => Operation::Subtract,
+                    TokenType::Star => Operation::Multiply,
+                    TokenType::Slash => Operation::Divide,
+                    _ => Operation::Compare,
+                };
+                let operand = self.show(&operand);
+                self.error(
+                    &operator,
+                    Message::CannotApply {
+                        operation,
+                        left: operand.clone(),
+                        right: operand,
+                    },
+                );
+            }
+        }
+    }
+
+    fn bind_pattern(&mut self, pattern: &Pattern, value: Type) {
+        match pattern {
+            Pattern::Identifier(name) => self.declare(name, Scheme::mono(value)),
+            Pattern::List { elements, rest, .. } => {
+                let value = self.resolve(&value);
+                for (index, element) in elements.iter().enumerate() {
+                    let item = match &value {
+                        Type::List(item) => (**item).clone(),
+                        Type::Tuple(items) => items.get(index).cloned().unwrap_or(Type::Dynamic),
+                        Type::String => Type::String,
+                        Type::Range => Type::Number,
+                        _ => Type::Dynamic,
+                    };
+                    if let Some(default) = &element.default {
+                        let default = self.infer_expression(default);
+                        self.unify(&item, &default);
+                    }
+                    self.bind_pattern(&element.pattern, item);
+                }
+                if let Some(rest) = rest {
+                    let items = match &value {
+                        Type::List(item) => Type::List(item.clone()),
+                        Type::String => Type::List(Box::new(Type::String)),
+                        Type::Range => Type::List(Box::new(Type::Number)),
+                        _ => Type::List(Box::new(Type::Dynamic)),
+                    };
+                    self.declare(rest, Scheme::mono(items));
+                }
+            }
+            Pattern::Object { fields, .. } => {
+                for field in fields {
+                    if let Some(default) = &field.default {
+                        self.infer_expression(default);
+                    }
+                    for name in field.pattern.names() {
+                        self.declare(name, Scheme::mono(Type::Dynamic));
+                    }
+                }
+            }
+        }
+    }
+
+    fn fresh(&mut self) -> Type {
+        self.bindings.push(None);
+        Type::Var(self.bindings.len() - 1)
+    }
+
+    // Follows bound type variables all the way down.
+    fn resolve(&self, t: &Type) -> Type {
+        match t {
+            Type::Var(var) => match &self.bindings[*var] {
+                Some(bound) => self.resolve(bound),
+                None => t.clone(),
+            },
+            Type::List(item) => Type::List(Box::new(self.resolve(item))),
+            Type::Set(item) => Type::Set(Box::new(self.resolve(item))),
+            Type::Tuple(items) => {
+                Type::Tuple(items.iter().map(|item| self.resolve(item)).collect())
+            }
+            Type::Function(params, returns) => Type::Function(
+                params.iter().map(|param| self.resolve(param)).collect(),
+                Box::new(self.resolve(returns)),
+            ),
+            _ => t.clone(),
+        }
+    }
+
+    fn unify(&mut self, a: &Type, b: &Type) -> bool {
+        match (self.resolve(a), self.resolve(b)) {
+            (Type::Dynamic, _) | (_, Type::Dynamic) => true,
+            (Type::Var(x), Type::Var(y)) if x == y => true,
+            (Type::Var(var), t) | (t, Type::Var(var)) => {
+                if occurs(var, &t) {
+                    return false;
+                }
+                self.bindings[var] = Some(t);
+                true
+            }
+            (Type::List(a), Type::List(b)) | (Type::Set(a), Type::Set(b)) => self.unify(&a, &b),
+            (Type::Tuple(a), Type::Tuple(b)) => {
+                a.len() == b.len() && a.iter().zip(&b).all(|(a, b)| self.unify(a, b))
+            }
+            (Type::Function(a_params, a_returns), Type::Function(b_params, b_returns)) => {
+                a_params.len() == b_params.len()
+                    && a_params
+                        .iter()
+                        .zip(&b_params)
+                        .all(|(a, b)| self.unify(a, b))
+                    && self.unify(&a_returns, &b_returns)
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    fn generalize(&self, t: &Type) -> Scheme {
+        let t = self.resolve(t);
+        let mut env_vars = Vec::new();
+        for scope in &self.scopes {
+            for scheme in scope.values() {
+                let body = self.resolve(&scheme.body);
+                free_vars(&body, &mut env_vars);
+                env_vars.retain(|var| !scheme.vars.contains(var));
+            }
+        }
+
+        let mut vars = Vec::new();
+        free_vars(&t, &mut vars);
+        vars.retain(|var| !env_vars.contains(var));
+
+        Scheme { vars, body: t }
+    }
+
+    fn instantiate(&mut self, scheme: &Scheme) -> Type {
+        let fresh = scheme
+            .vars
+            .iter()
+            .map(|var| (*var, self.fresh()))
+            .collect::<HashMap<usize, Type>>();
+        substitute(&self.resolve(&scheme.body), &fresh)
+    }
+
+    fn declare(&mut self, name: &Token, scheme: Scheme) {
+        if self.scopes.len() == 1 && !self.globals.contains(&name.lexeme) {
+            self.globals.push(name.lexeme.clone());
+        }
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), scheme);
+        }
+    }
+
+    fn lookup(&self, name: &Token) -> Option<Scheme> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).cloned())
+    }
+
+    fn display(&self, scheme: &Scheme) -> String {
+        let t = self.resolve(&scheme.body);
+        let mut vars = Vec::new();
+        free_vars(&t, &mut vars);
+        show(&t, &vars)
+    }
+
+    fn show(&self, t: &Type) -> String {
+        let t = self.resolve(t);
+        let mut vars = Vec::new();
+        free_vars(&t, &mut vars);
+        show(&t, &vars)
+    }
+
+    fn error(&mut self, at: &Token, message: Message) {
+        self.errors.push(Error::check(message).at(at));
+    }
+}
+
+fn annotated(annotation: &Token) -> Type {
+    match annotation.lexeme.as_str() {
+        "Tiro" => Type::Number,
+        "Qoraal" => Type::String,
+        "Xaqiiq" => Type::Bool,
+        "Waxba" => Type::Nil,
+        _ => Type::Dynamic,
+    }
+}
+
+fn kind(t: &Type) -> &'static str {
+    match t {
+        Type::Number => "Tiro",
+        Type::String => "Qoraal",
+        Type::Tuple(_) => "Koox",
+        Type::Set(_) => "Urur",
+        _ => "",
+    }
+}
+
+fn returns_value(statement: &Stmt) -> bool {
+    match statement {
+        Stmt::Return { value, .. } => value.is_some(),
+        Stmt::Block { statements } => statements.iter().any(returns_value),
+        Stmt::ForEach { body, .. } => body.iter().any(returns_value),
+        Stmt::Match { arms, .. } => arms.iter().any(|arm| arm.body.iter().any(returns_value)),
+        _ => false,
+    }
+}
+
+fn occurs(var: usize, t: &Type) -> bool {
+    let mut vars = Vec::new();
+    free_vars(t, &mut vars);
+    vars.contains(&var)
+}
+
+fn free_vars(t: &Type, vars: &mut Vec<usize>) {
+    match t {
+        Type::Var(var) if !vars.contains(var) => vars.push(*var),
+        Type::List(item) | Type::Set(item) => free_vars(item, vars),
+        Type::Tuple(items) => items.iter().for_each(|item| free_vars(item, vars)),
+        Type::Function(params, returns) => {
+            params.iter().for_each(|param| free_vars(param, vars));
+            free_vars(returns, vars);
+        }
+        _ => (),
+    }
+}
+
+fn substitute(t: &Type, fresh: &HashMap<usize, Type>) -> Type {
+    match t {
+        Type::Var(var) => fresh.get(var).cloned().unwrap_or(Type::Var(*var)),
+        Type::List(item) => Type::List(Box::new(substitute(item, fresh))),
+        Type::Set(item) => Type::Set(Box::new(substitute(item, fresh))),
+        Type::Tuple(items) => {
+            Type::Tuple(items.iter().map(|item| substitute(item, fresh)).collect())
+        }
+        Type::Function(params, returns) => Type::Function(
+            params
+                .iter()
+                .map(|param| substitute(param, fresh))
+                .collect(),
+            Box::new(substitute(returns, fresh)),
+        ),
+        _ => t.clone(),
+    }
+}
+
+// Type variables are named a, b, c, ... in order of appearance.
+fn show(t: &Type, vars: &[usize]) -> String {
+    match t {
+        Type::Var(var) => {
+            let index = vars.iter().position(|v| v == var).unwrap_or(0);
+            ((b'a' + (index % 26) as u8) as char).to_string()
+        }
+        Type::Number => "Tiro".to_string(),
+        Type::String => "Qoraal".to_string(),
+        Type::Bool => "Xaqiiq".to_string(),
+        Type::Nil => "Waxba".to_string(),
+        Type::Range => "Kala".to_string(),
+        Type::List(item) => format!("Liis[{}]", show(item, vars)),
+        Type::Set(item) => format!("Urur[{}]", show(item, vars)),
+        Type::Tuple(items) => {
+            let items = items
+                .iter()
+                .map(|item| show(item, vars))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("({})", items)
+        }
+        Type::Function(params, returns) => {
+            let params = params
+                .iter()
+                .map(|param| show(param, vars))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!("hawl({}) -> {}", params, show(returns, vars))
+        }
+        Type::Dynamic => "Wax".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn infer(source: &str) -> Result<Vec<String>> {
+        let tokens = Lexer::new(source).lex()?;
+        let statements = Parser::new(tokens).parse()?;
+        Inference::new().infer(&statements)
+    }
+
+    // Each error with the line its span starts on.
+    fn errors(source: &str) -> Vec<String> {
+        let error = infer(source).unwrap_err();
+        let errors = error.downcast::<Errors>().unwrap();
+        errors
+            .0
+            .iter()
+            .map(|error| format!("{}: {}", error.span.unwrap().line + 1, error))
+            .collect()
+    }
+
+    #[test]
+    fn test_infers_signatures() {
+        let types = infer(
+            "hawl kordhi(x) { celi x + 1; }
+             hawl aqoonsi(x) { celi x; }
+             hawl fac(n) { weel r = n * fac(n - 1); celi r; }
+             hawl samee(n) { hawl kudar(x) { celi x + n; } celi kudar; }
+             weel a = aqoonsi(1);
+             weel b = aqoonsi(\"s\");
+             weel k = samee(2);
+             weel t = (a, [b]);",
+        )
+        .unwrap();
+
+        assert_eq!(
+            types,
+            [
+                "kordhi: hawl(Tiro) -> Tiro",
+                "aqoonsi: hawl(a) -> a",
+                "fac: hawl(Tiro) -> Tiro",
+                "samee: hawl(a) -> hawl(a) -> a",
+                "a: Tiro",
+                "b: Qoraal",
+                "k: hawl(Tiro) -> Tiro",
+                "t: (Tiro, Liis[Qoraal])",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_type_errors() {
+        let errors = errors(
+            "weel x = \"a\" - 1;
+             weel n = 5;
+             weel y = n(2);
+             hawl g(v) { celi v; }
+             weel w = g(1) + g(\"c\");
+             hawl h(a: Tiro = \"b\") { celi a; }
+             weel z: Qoraal = h(1, 2);",
+        );
+
+        assert_eq!(
+            errors,
+            [
+                "1: Cannot subtract Qoraal and Tiro",
+                "3: Cannot call Tiro",
+                "5: Cannot add Tiro and Qoraal",
+                "6: Default for parameter 'a' of 'h' expects Tiro but got Qoraal",
+                "7: Function 'h' expects at most 1 arguments but got 2",
+                "7: Type annotation expects Qoraal but got Tiro",
+            ]
+        );
+    }
+}
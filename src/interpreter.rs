@@ -1,27 +1,109 @@
-use anyhow::Result;
-
 use crate::{
-    environment::Environment,
-    expr::{ExpLiteralValue, Expr},
+    environment::Environment, error::Result, native, routine::Routine, scheduler::Scheduler,
     statement::Stmt,
 };
 
 pub struct Interpreter {
     environment: Environment,
+    scheduler: Scheduler,
 }
 
 impl Interpreter {
     pub fn new() -> Self {
+        let mut environment = Environment::new();
+        native::define_globals(&mut environment);
+
         Self {
-            environment: Environment::new(),
+            environment,
+            scheduler: Scheduler::new(),
         }
     }
 
     pub fn interpret(&mut self, stmts: Vec<Stmt>) -> Result<()> {
-        for stmt in stmts {
-            stmt.execute(&mut self.environment)?;
+        let main = Routine::new(stmts, std::mem::take(&mut self.environment));
+        self.scheduler.spawn_main(main);
+
+        let result = self.scheduler.run();
+
+        if let Some(main) = self.scheduler.finish() {
+            self.environment = main.into_environment();
         }
 
-        Ok(())
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expr::ExpLiteralValue, lexer::Lexer, parser::Parser};
+
+    fn interpret(interpreter: &mut Interpreter, source: &str) -> Result<()> {
+        let tokens = Lexer::new(source).lex()?;
+        let statements = Parser::new(tokens).parse()?;
+        interpreter.interpret(statements)
+    }
+
+    #[test]
+    fn test_tasks_run_in_deterministic_order() {
+        let mut interpreter = Interpreter::new();
+        interpret(
+            &mut interpreter,
+            "hawl qore(magac, ch) { markasta i ku 0..2 { dir magac ku ch; } }
+             weel ch = kanaal(0);
+             bilow qore(\"a\", ch);
+             bilow qore(\"b\", ch);
+             weel taariikh = \"\";
+             markasta i ku 0..4 { qaado x ka ch; taariikh = taariikh + x; }",
+        )
+        .unwrap();
+
+        assert_eq!(
+            interpreter.environment.get("taariikh"),
+            Some(&ExpLiteralValue::StringValue("abab".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_buffered_channel() {
+        let mut interpreter = Interpreter::new();
+        interpret(
+            &mut interpreter,
+            "weel ch = kanaal(2);
+             dir 1 ku ch;
+             dir 2 ku ch;
+             qaado a ka ch;
+             qaado b ka ch;
+             weel wadarta = a + b;",
+        )
+        .unwrap();
+
+        assert_eq!(
+            interpreter.environment.get("wadarta"),
+            Some(&ExpLiteralValue::Number(3.0))
+        );
+    }
+
+    #[test]
+    fn test_deadlock_lists_blocked_tasks() {
+        let mut interpreter = Interpreter::new();
+        let error = interpret(
+            &mut interpreter,
+            "hawl qore(ch) { dir 1 ku ch; dir 2 ku ch; }
+             weel ch = kanaal(0);
+             bilow qore(ch);
+             qaado x ka ch;
+             qaado y ka kanaal(0);",
+        )
+        .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Deadlock: every task is blocked: main#0 (waiting to receive), qore#1 (waiting to send)"
+        );
+        assert_eq!(
+            interpreter.environment.get("x"),
+            Some(&ExpLiteralValue::Number(1.0))
+        );
     }
 }
@@ -0,0 +1,98 @@
+use crate::{
+    error::{Error, Result},
+    expr::ExpLiteralValue,
+    message::Message,
+};
+
+/// A lazy sequence of values produced by iterating over an `ExpLiteralValue`.
+///
+/// Items are fallible so that iterables backed by user code can report
+/// runtime errors in the middle of a loop.
+pub type ValueIterator = Box<dyn Iterator<Item = Result<ExpLiteralValue>>>;
+
+/// Returns an iterator over any iterable value: lists, tuples, sets, strings,
+/// ranges and generators.
+///
+/// User classes are meant to join by defining a `xiga` method, called until
+/// it returns `waxba`. There are no classes yet, so there is no arm for them.
+pub fn iterate(value: &ExpLiteralValue) -> Result<ValueIterator> {
+    match value {
+        ExpLiteralValue::ListValue(items) | ExpLiteralValue::TupleValue(items) => {
+            Ok(Box::new(items.clone().into_iter().map(Ok)))
+        }
+        ExpLiteralValue::SetValue(set) => Ok(Box::new(
+            set.iter()
+                .cloned()
+                .collect::<Vec<ExpLiteralValue>>()
+                .into_iter()
+                .map(Ok),
+        )),
+        ExpLiteralValue::StringValue(s) => {
+            Ok(Box::new(s.chars().collect::<Vec<char>>().into_iter().map(
+                |ch| Ok(ExpLiteralValue::StringValue(ch.to_string())),
+            )))
+        }
+        ExpLiteralValue::RangeValue(range) => Ok(Box::new(range.iter().map(Ok))),
+        ExpLiteralValue::GeneratorValue(generator) => Ok(generator.iter()),
+        _ => Err(Error::runtime(Message::NotIterable(value.to_string()))),
+    }
+}
+
+/// The largest magnitude up to which f32 holds every integer exactly.
+pub const MAX_EXACT: f32 = 16_777_216.0;
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct Range {
+    start: f32,
+    end: f32,
+    inclusive: bool,
+}
+
+impl Range {
+    pub fn new(start: f32, end: f32, inclusive: bool) -> Result<Self, Error> {
+        // Past the limit, neighbouring elements would round to the same f32.
+        // Written so that NaN bounds are rejected too.
+        if !(start.abs() <= MAX_EXACT && end.abs() <= MAX_EXACT) {
+            return Err(Error::runtime(Message::RangeTooLarge {
+                from: start.to_string(),
+                to: end.to_string(),
+            }));
+        }
+
+        Ok(Self {
+            start,
+            end,
+            inclusive,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        let span = (self.end - self.start) as f64;
+        if span < 0.0 {
+            return 0;
+        }
+
+        if self.inclusive {
+            span.floor() as usize + 1
+        } else {
+            span.ceil() as usize
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Yields `start`, `start + 1`, ... without allocating the whole range.
+    pub fn iter(&self) -> impl Iterator<Item = ExpLiteralValue> {
+        let start = self.start as f64;
+        (0..self.len()).map(move |i| ExpLiteralValue::Number((start + i as f64) as f32))
+    }
+}
+
+impl std::fmt::Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let operator = if self.inclusive { "..=" } else { ".." };
+        write!(f, "{}{}{}", self.start, operator, self.end)
+    }
+}
@@ -1,38 +1,57 @@
-use anyhow::Result;
+use std::{collections::VecDeque, iter::FusedIterator};
+
+use crate::{
+    dialect::{Dialect, SOMALI},
+    error::{Error, Result, Span},
+    message::Message,
+    numerals::digit_value,
+    trace::{self, Phase},
+};
+use unicode_ident::{is_xid_continue, is_xid_start};
 
 pub struct Lexer {
     input: String,
-    tokens: Vec<Token>,
+    dialect: &'static Dialect,
+    pending: VecDeque<Token>,
+    docs: Vec<String>,
+    finished: bool,
     start: usize,
     current: usize,
-    position: usize,
+    line: usize,
+    line_start: usize,
+    token_line: usize,
+    token_column: usize,
 }
 
 impl Lexer {
+    // The binary always knows its dialect; `new` is for tests and callers
+    // happy with Somali or whatever the file's pragma asks for.
+    #[allow(dead_code)]
     pub fn new(input: &str) -> Lexer {
+        Self::with_dialect(input, &SOMALI)
+    }
+
+    /// A lexer for `input` in its pragma's dialect, or `default` if it has
+    /// none.
+    pub fn with_dialect(input: &str, default: &'static Dialect) -> Lexer {
+        let dialect = Dialect::for_source(input, default);
         Lexer {
             input: input.to_string(),
-            tokens: Vec::new(),
+            dialect,
+            pending: VecDeque::new(),
+            docs: Vec::new(),
+            finished: false,
             start: 0,
             current: 0,
-            position: 0,
+            line: 0,
+            line_start: 0,
+            token_line: 0,
+            token_column: 0,
         }
     }
 
     pub fn lex(&mut self) -> Result<Vec<Token>> {
-        while !self.is_at_end() {
-            self.start = self.current;
-            self.lex_token();
-        }
-
-        self.tokens.push(Token::new(
-            TokenType::EOF,
-            "".to_string(),
-            None,
-            self.position,
-        ));
-
-        Ok(self.tokens.clone())
+        self.collect()
     }
 
     fn lex_token(&mut self) -> Result<()> {
@@ -43,12 +62,37 @@ impl Lexer {
             ')' => self.add_token(TokenType::RightParen),
             '{' => self.add_token(TokenType::LeftBrace),
             '}' => self.add_token(TokenType::RightBrace),
+            '[' => self.add_token(TokenType::LeftBracket),
+            ']' => self.add_token(TokenType::RightBracket),
             ',' => self.add_token(TokenType::Comma),
-            '.' => self.add_token(TokenType::Dot),
+            '.' => {
+                if self.match_char('.') {
+                    if self.match_char('.') {
+                        self.add_token(TokenType::DotDotDot);
+                    } else if self.match_char('=') {
+                        self.add_token(TokenType::DotDotEqual);
+                    } else {
+                        self.add_token(TokenType::DotDot);
+                    }
+                } else {
+                    self.add_token(TokenType::Dot);
+                }
+            }
             '-' => self.add_token(TokenType::Minus),
             '+' => self.add_token(TokenType::Plus),
             ';' => self.add_token(TokenType::Semicolon),
+            ':' => self.add_token(TokenType::Colon),
             '*' => self.add_token(TokenType::Star),
+            '?' => {
+                if self.match_char('?') {
+                    self.add_token(TokenType::QuestionQuestion);
+                } else if self.peek() == '.' && !self.peek_next().is_ascii_digit() {
+                    self.advance();
+                    self.add_token(TokenType::QuestionDot);
+                } else {
+                    self.add_token(TokenType::Question);
+                }
+            }
             '!' => {
                 if self.match_char('=') {
                     self.add_token(TokenType::BangEqual);
@@ -79,23 +123,35 @@ impl Lexer {
             }
             '/' => {
                 if self.match_char('/') {
+                    // `///` starts a doc comment, but `////` is an ordinary one.
+                    let doc = self.peek() == '/' && self.peek_next() != '/';
                     while self.peek() != '\n' && !self.is_at_end() {
                         self.advance();
                     }
+                    if doc {
+                        let text = &self.input[self.start + 3..self.current];
+                        let text = text.strip_prefix(' ').unwrap_or(text);
+                        self.docs.push(text.trim_end().to_string());
+                    }
+                } else if self.match_char('*') {
+                    self.block_comment()?;
                 } else {
                     self.add_token(TokenType::Slash);
                 }
             }
             ' ' | '\r' | '\t' => (),
-            '\n' => self.position += 1,
-            '"' => self.string()?,
+            '\n' => self.newline(),
+            '"' | '\u{201C}' | '\u{2018}' => self.quoted(ch, false)?,
             cha => {
-                if cha.is_digit(10) {
-                    self.number();
-                } else if cha.is_alphabetic() {
-                    self.identifier();
+                if cha == 'r' && matches!(self.peek(), '"' | '\u{201C}' | '\u{2018}') {
+                    let open = self.advance();
+                    self.quoted(open, true)?;
+                } else if digit_value(cha).is_some() {
+                    self.number()?;
+                } else if cha == '_' || is_xid_start(cha) {
+                    self.identifier()?;
                 } else {
-                    eprintln!("ERROR: Unexpected character: {}", cha);
+                    return Err(self.error(Message::UnexpectedCharacter(cha)));
                 }
             }
         }
@@ -103,30 +159,13 @@ impl Lexer {
     }
 
     fn identifier(&mut self) -> Result<()> {
-        while self.peek().is_alphanumeric() {
+        while is_xid_continue(self.peek()) {
             self.advance();
         }
 
         let text = &self.input[self.start..self.current];
 
-        let token_type = match text {
-            "qeyb" => TokenType::Class,
-            "markasta" => TokenType::For,
-            "kale" => TokenType::Else,
-            "been" => TokenType::False,
-            "hawl" => TokenType::Fun,
-            "haddii" => TokenType::If,
-            "waxba" => TokenType::Nil,
-            "ama" => TokenType::Or,
-            "daabac" => TokenType::Print,
-            "celi" => TokenType::Return,
-            "super" => TokenType::Super,
-            "kan" => TokenType::This,
-            "run" => TokenType::True,
-            "weel" => TokenType::Var,
-            "inta" => TokenType::While,
-            _ => TokenType::Identifier,
-        };
+        let token_type = self.dialect.keyword(text).unwrap_or(TokenType::Identifier);
 
         self.add_token(token_type);
 
@@ -134,102 +173,392 @@ impl Lexer {
     }
 
     fn number(&mut self) -> Result<()> {
-        while self.peek().is_digit(10) {
-            self.advance();
-        }
+        let radix = match (&self.input[self.start..self.current], self.peek()) {
+            ("0", 'x' | 'X') => 16,
+            ("0", 'o' | 'O') => 8,
+            ("0", 'b' | 'B') => 2,
+            _ => 10,
+        };
 
-        if self.peek() == '.' && self.peek_next().is_digit(10) {
+        if radix != 10 {
             self.advance();
+            if self.digits(radix, 0)? == 0 {
+                let text = self.input[self.start..self.current].to_string();
+                return Err(self.error(Message::MissingDigits(text)));
+            }
+        } else {
+            self.digits(10, 1)?;
+
+            if self.peek() == '.' && digit_value(self.peek_next()).is_some() {
+                self.advance();
+                self.digits(10, 0)?;
+            }
 
-            while self.peek().is_digit(10) {
+            if matches!(self.peek(), 'e' | 'E') {
                 self.advance();
+                if matches!(self.peek(), '+' | '-') {
+                    self.advance();
+                }
+                if self.digits(10, 0)? == 0 {
+                    let text = self.input[self.start..self.current].to_string();
+                    return Err(self.error(Message::MissingDigits(text)));
+                }
             }
         }
 
-        let value = self.input[self.start..self.current].parse::<f32>()?;
+        // Separators are dropped and Osmanya digits read as their ASCII
+        // counterparts before the text is parsed.
+        let text = &self.input[self.start..self.current];
+        let digits: String = text
+            .chars()
+            .skip(if radix == 10 { 0 } else { 2 })
+            .filter(|c| *c != '_')
+            .map(|c| match digit_value(c) {
+                Some(digit) => char::from_digit(digit, 10).unwrap(),
+                None => c,
+            })
+            .collect();
+        let value = if radix == 10 {
+            digits.parse::<f32>().ok().filter(|value| value.is_finite())
+        } else {
+            u64::from_str_radix(&digits, radix)
+                .ok()
+                .map(|value| value as f32)
+        };
+        let Some(value) = value else {
+            return Err(self.error(Message::InvalidNumber(text.to_string())));
+        };
         self.add_token_literal(TokenType::Number, LiteralValue::FloatValue(value));
 
         Ok(())
     }
 
-    fn peek_next(&self) -> char {
-        if self.current + 1 >= self.input.len() {
-            return '\0';
+    // Consumes a run of digits in `radix`, allowing `_` between digits, and
+    // returns how many digits it saw including the `seen` already consumed.
+    fn digits(&mut self, radix: u32, seen: usize) -> Result<usize> {
+        let mut count = seen;
+        loop {
+            let c = self.peek();
+            if radix_digit(c, radix).is_some() {
+                count += 1;
+            } else if c == '_' {
+                if count == 0 || radix_digit(self.peek_next(), radix).is_none() {
+                    self.advance();
+                    return Err(self.char_error(Message::MisplacedSeparator, c));
+                }
+            } else if radix != 10 && is_xid_continue(c) {
+                self.advance();
+                return Err(self.char_error(Message::InvalidDigit(c, radix), c));
+            } else {
+                return Ok(count);
+            }
+            self.advance();
         }
-        self.input.chars().nth(self.current + 1).unwrap()
     }
 
-    fn string(&mut self) -> Result<()> {
-        while self.peek() != '"' && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.position += 1;
-            }
-            self.advance();
+    fn peek_next(&self) -> char {
+        self.input[self.current..].chars().nth(1).unwrap_or('\0')
+    }
+
+    // Phone keyboards insert curly quotes, and often mix them with straight
+    // ones, so a double-quoted string may close with either `"` or `”`.
+    // Raw strings (`r"..."`) keep backslashes as they are.
+    fn quoted(&mut self, open: char, raw: bool) -> Result<()> {
+        if open == '"' && self.input[self.current..].starts_with("\"\"") {
+            self.current += 2;
+            return self.triple_quoted(raw);
+        }
+
+        let closing: &[char] = match open {
+            '\u{2018}' => &['\u{2019}'],
+            _ => &['"', '\u{201D}'],
+        };
+
+        let body_start = self.current;
+        while !closing.contains(&self.peek()) && !self.is_at_end() {
+            self.string_char(raw);
         }
 
         if self.is_at_end() {
-            eprintln!("ERROR: Unterminated string");
-            return Ok(());
+            return Err(self.error(Message::UnterminatedString));
         }
 
+        let body = &self.input[body_start..self.current];
+        let value = if raw {
+            body.to_string()
+        } else {
+            unescape(body).map_err(|message| self.error(message))?
+        };
         self.advance();
 
-        let value = self.input[self.start + 1..self.current - 1].to_string();
         self.add_token_literal(TokenType::StringLit, LiteralValue::StringValue(value));
 
         Ok(())
     }
 
-    fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
+    // `"""` strings may span lines. A line break straight after the opening
+    // quotes and the indentation the lines share are not part of the value.
+    fn triple_quoted(&mut self, raw: bool) -> Result<()> {
+        let body_start = self.current;
+        while !self.input[self.current..].starts_with("\"\"\"") {
+            if self.is_at_end() {
+                return Err(self.error(Message::UnterminatedString));
+            }
+            self.string_char(raw);
         }
-        self.input.chars().nth(self.current).unwrap()
+
+        let body = dedent(&self.input[body_start..self.current]);
+        let value = if raw {
+            body
+        } else {
+            unescape(&body).map_err(|message| self.error(message))?
+        };
+        self.current += 3;
+
+        self.add_token_literal(TokenType::StringLit, LiteralValue::StringValue(value));
+
+        Ok(())
+    }
+
+    // Steps over one character of a string body, taking an escaped
+    // character with its backslash so an escaped quote does not end it.
+    fn string_char(&mut self, raw: bool) {
+        let mut c = self.advance();
+        if c == '\\' && !raw && !self.is_at_end() {
+            c = self.advance();
+        }
+        if c == '\n' {
+            self.newline();
+        }
+    }
+
+    fn peek(&self) -> char {
+        self.input[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn match_char(&mut self, expected: char) -> bool {
         if self.is_at_end() {
             return false;
         }
-        if self.input.chars().nth(self.current).unwrap() != expected {
+        if self.peek() != expected {
             return false;
         }
 
-        self.current += 1;
+        self.current += expected.len_utf8();
         true
     }
 
+    // Block comments nest, so `/* a /* b */ c */` is a single comment.
+    fn block_comment(&mut self) -> Result<()> {
+        let mut depth = 1;
+        while depth > 0 {
+            if self.is_at_end() {
+                return Err(Error::lex(Message::UnterminatedComment {
+                    line: self.token_line + 1,
+                })
+                .with_span(Span {
+                    line: self.token_line,
+                    column: self.token_column,
+                    offset: self.start,
+                    length: 2,
+                }));
+            }
+
+            match self.advance() {
+                '/' if self.match_char('*') => depth += 1,
+                '*' if self.match_char('/') => depth -= 1,
+                '\n' => self.newline(),
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
     fn add_token(&mut self, token_type: TokenType) {
-        let text = &self.input[self.start..self.current];
-        self.tokens.push(Token::new(
-            token_type,
-            text.to_string(),
-            None,
-            self.position,
-        ));
+        self.push_token(token_type, None);
     }
 
     fn add_token_literal(&mut self, token_type: TokenType, literal: LiteralValue) {
+        self.push_token(token_type, Some(literal));
+    }
+
+    // Doc comments read since the last token are attached to this one.
+    fn push_token(&mut self, token_type: TokenType, literal: Option<LiteralValue>) {
         let text = &self.input[self.start..self.current];
-        self.tokens.push(Token::new(
+        let mut token = Token::new(
             token_type,
             text.to_string(),
-            Some(literal),
-            self.position,
-        ));
+            literal,
+            self.token_line,
+            self.token_column,
+            self.start,
+        );
+        if !self.docs.is_empty() {
+            token.doc = Some(self.docs.join("\n"));
+            self.docs.clear();
+        }
+        self.pending.push_back(token);
     }
 
     fn is_at_end(&self) -> bool {
         self.current >= self.input.len()
     }
 
+    fn error(&self, message: Message) -> Error {
+        let length = self.current - self.start;
+        Error::lex(message).with_span(Span {
+            line: self.token_line,
+            column: self.token_column,
+            offset: self.start,
+            length,
+        })
+    }
+
+    // An error pointing at `c`, the character just consumed.
+    fn char_error(&self, message: Message, c: char) -> Error {
+        let offset = self.current - c.len_utf8();
+        Error::lex(message).with_span(Span {
+            line: self.line,
+            column: offset - self.line_start,
+            offset,
+            length: c.len_utf8(),
+        })
+    }
+
+    fn newline(&mut self) {
+        self.line += 1;
+        self.line_start = self.current;
+    }
+
     fn advance(&mut self) -> char {
-        let c = self.input.chars().nth(self.current).unwrap();
-        self.current += 1;
+        let c = self.peek();
+        self.current += c.len_utf8();
         c
     }
 }
 
+fn radix_digit(c: char, radix: u32) -> Option<u32> {
+    if radix == 10 {
+        digit_value(c)
+    } else if c.is_ascii() {
+        c.to_digit(radix)
+    } else {
+        None
+    }
+}
+
+fn unescape(body: &str) -> std::result::Result<String, Message> {
+    let mut value = String::with_capacity(body.len());
+    let mut chars = body.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some('r') => value.push('\r'),
+            Some('0') => value.push('\0'),
+            Some(c @ ('\\' | '"' | '\'' | '\u{201D}' | '\u{2019}')) => value.push(c),
+            Some('u') => {
+                let rest = chars.as_str();
+                let escape = rest
+                    .strip_prefix('{')
+                    .and_then(|rest| rest.find('}').map(|end| &rest[..end]));
+                let Some(digits) = escape else {
+                    return Err(Message::InvalidUnicodeEscape("\\u".to_string()));
+                };
+                let decoded = Some(digits)
+                    .filter(|digits| (1..=6).contains(&digits.len()))
+                    .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+                    .and_then(char::from_u32);
+
+                match decoded {
+                    Some(c) => value.push(c),
+                    None => {
+                        return Err(Message::InvalidUnicodeEscape(format!("\\u{{{}}}", digits)))
+                    }
+                }
+                chars = rest[digits.len() + 2..].chars();
+            }
+            Some(c) => return Err(Message::UnknownEscape(c)),
+            None => return Err(Message::UnterminatedString),
+        }
+    }
+
+    Ok(value)
+}
+
+fn dedent(body: &str) -> String {
+    let body = body
+        .strip_prefix("\r\n")
+        .or_else(|| body.strip_prefix('\n'))
+        .unwrap_or(body);
+
+    let mut lines: Vec<&str> = body.split('\n').collect();
+    if lines.len() > 1 && lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end_matches('\r'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Tokens are produced one at a time, so a caller can stop early or handle
+// each token as it arrives. After an error, lexing carries on from the
+// next character; the last item is always the `Eof` token.
+impl Iterator for Lexer {
+    type Item = Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(token) = self.pending.pop_front() {
+                trace::event(Phase::Lex, || {
+                    format!(
+                        "{:?} {:?} at {}:{}",
+                        token.token_type,
+                        token.lexeme,
+                        token.line_number + 1,
+                        token.column + 1
+                    )
+                });
+                return Some(Ok(token));
+            }
+            if self.finished {
+                return None;
+            }
+
+            self.start = self.current;
+            self.token_line = self.line;
+            self.token_column = self.start - self.line_start;
+
+            if self.is_at_end() {
+                self.add_token(TokenType::Eof);
+                self.finished = true;
+            } else if let Err(error) = self.lex_token() {
+                return Some(Err(error));
+            }
+        }
+    }
+}
+
+impl FusedIterator for Lexer {}
+
+#[allow(dead_code)]
 #[derive(Debug, Clone, PartialEq, PartialOrd)]
 pub enum LiteralValue {
     IntValue(i64),
@@ -244,6 +573,10 @@ pub struct Token {
     pub lexeme: String,
     pub literal: Option<LiteralValue>,
     pub line_number: usize,
+    pub column: usize,
+    pub offset: usize,
+    /// The `///` comment lines just before this token, if any.
+    pub doc: Option<String>,
 }
 
 impl std::fmt::Display for TokenType {
@@ -258,17 +591,24 @@ impl Token {
         lexeme: String,
         literal: Option<LiteralValue>,
         line_number: usize,
+        column: usize,
+        offset: usize,
     ) -> Token {
         Token {
             token_type,
             lexeme,
             literal,
             line_number,
+            column,
+            offset,
+            doc: None,
         }
     }
+}
 
-    pub fn to_string(&self) -> String {
-        format!("{} {} {:?}", self.token_type, self.lexeme, self.literal)
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} {} {:?}", self.token_type, self.lexeme, self.literal)
     }
 }
 
@@ -278,12 +618,18 @@ pub enum TokenType {
     RightParen,
     LeftBrace,
     RightBrace,
+    LeftBracket,
+    RightBracket,
 
     Comma,
     Dot,
+    DotDot,
+    DotDotDot,
+    DotDotEqual,
     Minus,
     Plus,
     Semicolon,
+    Colon,
     Slash,
     Star,
 
@@ -295,6 +641,9 @@ pub enum TokenType {
     GreaterEqual,
     Less,
     LessEqual,
+    Question,
+    QuestionQuestion,
+    QuestionDot,
 
     Identifier,
     StringLit,
@@ -302,22 +651,31 @@ pub enum TokenType {
 
     And,
     Class,
+    Const,
     Else,
+    Enum,
     False,
     Fun,
     For,
+    From,
     If,
+    In,
+    Match,
     Nil,
     Or,
     Print,
+    Receive,
     Return,
+    Send,
+    Spawn,
     Super,
     This,
     True,
     Var,
     While,
+    Yield,
 
-    EOF,
+    Eof,
 }
 
 #[cfg(test)]
@@ -342,7 +700,7 @@ mod tests {
             TokenType::Semicolon,
             TokenType::Star,
             TokenType::Slash,
-            TokenType::EOF,
+            TokenType::Eof,
         ];
 
         let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
@@ -365,7 +723,64 @@ mod tests {
             TokenType::LessEqual,
             TokenType::Greater,
             TokenType::GreaterEqual,
-            TokenType::EOF,
+            TokenType::Eof,
+        ];
+
+        let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
+
+        assert_eq!(actual_types, expected_types);
+    }
+
+    #[test]
+    fn question_tokens() {
+        let source = "a ? b : c ?? d?.e?.[0] ?.5";
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.lex().unwrap();
+
+        let expected_types = vec![
+            TokenType::Identifier,
+            TokenType::Question,
+            TokenType::Identifier,
+            TokenType::Colon,
+            TokenType::Identifier,
+            TokenType::QuestionQuestion,
+            TokenType::Identifier,
+            TokenType::QuestionDot,
+            TokenType::Identifier,
+            TokenType::QuestionDot,
+            TokenType::LeftBracket,
+            TokenType::Number,
+            TokenType::RightBracket,
+            TokenType::Question,
+            TokenType::Dot,
+            TokenType::Number,
+            TokenType::Eof,
+        ];
+
+        let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
+
+        assert_eq!(actual_types, expected_types);
+    }
+
+    #[test]
+    fn range_tokens() {
+        let source = "1..10 1..=10 1.5..2 ...rest";
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.lex().unwrap();
+
+        let expected_types = vec![
+            TokenType::Number,
+            TokenType::DotDot,
+            TokenType::Number,
+            TokenType::Number,
+            TokenType::DotDotEqual,
+            TokenType::Number,
+            TokenType::Number,
+            TokenType::DotDot,
+            TokenType::Number,
+            TokenType::DotDotDot,
+            TokenType::Identifier,
+            TokenType::Eof,
         ];
 
         let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
@@ -379,7 +794,7 @@ mod tests {
         let mut lexer = Lexer::new(source);
         let tokens = lexer.lex().unwrap();
 
-        let expected_types = vec![TokenType::EOF];
+        let expected_types = vec![TokenType::Eof];
 
         let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
 
@@ -392,7 +807,7 @@ mod tests {
         let mut lexer = Lexer::new(source);
         let tokens = lexer.lex().unwrap();
 
-        let expected_types = vec![TokenType::Number, TokenType::Number, TokenType::EOF];
+        let expected_types = vec![TokenType::Number, TokenType::Number, TokenType::Eof];
 
         let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
 
@@ -405,7 +820,7 @@ mod tests {
         let mut lexer = Lexer::new(source);
         let tokens = lexer.lex().unwrap();
 
-        let expected_types = vec![TokenType::StringLit, TokenType::EOF];
+        let expected_types = vec![TokenType::StringLit, TokenType::Eof];
 
         let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
 
@@ -422,7 +837,7 @@ mod tests {
             TokenType::Identifier,
             TokenType::Identifier,
             TokenType::Identifier,
-            TokenType::EOF,
+            TokenType::Eof,
         ];
 
         let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
@@ -433,7 +848,7 @@ mod tests {
     #[test]
     fn keyword_tokens() {
         let source =
-            "qeyb markasta haddii kale been hawl waxba ama daabac celi super kan run weel inta";
+            "qeyb markasta haddii kale been hawl waxba ama daabac celi super kan run weel inta nooc ku bixi bilow dir qaado ka joogto dooro iyo";
         let mut lexer = Lexer::new(source);
         let tokens = lexer.lex().unwrap();
 
@@ -453,7 +868,17 @@ mod tests {
             TokenType::True,
             TokenType::Var,
             TokenType::While,
-            TokenType::EOF,
+            TokenType::Enum,
+            TokenType::In,
+            TokenType::Yield,
+            TokenType::Spawn,
+            TokenType::Send,
+            TokenType::Receive,
+            TokenType::From,
+            TokenType::Const,
+            TokenType::Match,
+            TokenType::And,
+            TokenType::Eof,
         ];
 
         let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
@@ -485,11 +910,304 @@ mod tests {
             TokenType::Identifier,
             TokenType::Semicolon,
             TokenType::RightBrace,
-            TokenType::EOF,
+            TokenType::Eof,
         ];
 
         let actual_types: Vec<TokenType> = tokens.into_iter().map(|t| t.token_type).collect();
 
         assert_eq!(actual_types, expected_types);
     }
+
+    #[test]
+    fn token_positions() {
+        let source = "weel s = \"a\nb\";\n  daabac s;";
+        let tokens = Lexer::new(source).lex().unwrap();
+
+        let positions: Vec<(&str, usize, usize, usize)> = tokens
+            .iter()
+            .map(|t| (t.lexeme.as_str(), t.line_number, t.column, t.offset))
+            .collect();
+
+        assert_eq!(
+            positions,
+            vec![
+                ("weel", 0, 0, 0),
+                ("s", 0, 5, 5),
+                ("=", 0, 7, 7),
+                ("\"a\nb\"", 0, 9, 9),
+                (";", 1, 2, 14),
+                ("daabac", 2, 2, 18),
+                ("s", 2, 9, 25),
+                (";", 2, 10, 26),
+                ("", 2, 11, 27),
+            ]
+        );
+    }
+
+    #[test]
+    fn osmanya_numbers_and_names() {
+        let source = "\u{1049b}\u{10486} = \u{104a1}\u{104a2}.\u{104a5}; \"ma\u{e1}\"";
+        let tokens = Lexer::new(source).lex().unwrap();
+
+        assert_eq!(tokens[0].token_type, TokenType::Identifier);
+        assert_eq!(tokens[0].lexeme, "\u{1049b}\u{10486}");
+        assert_eq!(tokens[2].literal, Some(LiteralValue::FloatValue(12.5)));
+        assert_eq!(
+            tokens[4].literal,
+            Some(LiteralValue::StringValue("ma\u{e1}".to_string()))
+        );
+    }
+
+    // The example program from the README, written as a phone keyboard
+    // would type it.
+    fn readme_example() -> &'static str {
+        let readme = include_str!("../README.md");
+        let start = readme.find("```javascript\n").unwrap() + "```javascript\n".len();
+        let end = start + readme[start..].find("```").unwrap();
+        &readme[start..end]
+    }
+
+    #[test]
+    fn readme_identifiers_with_underscores() {
+        let tokens = Lexer::new(readme_example()).lex().unwrap();
+        let identifiers: Vec<&str> = tokens
+            .iter()
+            .filter(|t| t.token_type == TokenType::Identifier)
+            .map(|t| t.lexeme.as_str())
+            .collect();
+
+        assert_eq!(
+            identifiers,
+            vec![
+                "soo_dhoweey",
+                "magac",
+                "magac",
+                "magac",
+                "soo_dhoweey",
+                "magac",
+                "wey_kujirtaa",
+                "haa",
+                "wey_kujirtaa",
+            ]
+        );
+
+        let tokens = Lexer::new("_ x_1 _tmp").lex().unwrap();
+        assert!(tokens[..3]
+            .iter()
+            .all(|t| t.token_type == TokenType::Identifier));
+    }
+
+    #[test]
+    fn readme_curly_quoted_strings() {
+        let tokens = Lexer::new(readme_example()).lex().unwrap();
+        let strings: Vec<LiteralValue> = tokens
+            .into_iter()
+            .filter(|t| t.token_type == TokenType::StringLit)
+            .filter_map(|t| t.literal)
+            .collect();
+
+        let expected = [
+            "Soo dhowoow ",
+            "Ahmed",
+            "Tiradu waa sax",
+            "Tiradu ma aha sax",
+        ];
+        assert_eq!(
+            strings,
+            expected
+                .iter()
+                .map(|s| LiteralValue::StringValue(s.to_string()))
+                .collect::<Vec<_>>()
+        );
+
+        let tokens = Lexer::new("\u{2018}haa\u{2019}").lex().unwrap();
+        assert_eq!(
+            tokens[0].literal,
+            Some(LiteralValue::StringValue("haa".to_string()))
+        );
+        assert!(Lexer::new("\u{201C}furan").lex().is_err());
+    }
+
+    #[test]
+    fn streams_tokens_and_continues_after_errors() {
+        let mut lexer = Lexer::new("weel x = 1 @ 2;");
+
+        let first = lexer.next().unwrap().unwrap();
+        assert_eq!(first.token_type, TokenType::Var);
+
+        let rest: Vec<Result<Token>> = lexer.collect();
+        let types: Vec<Option<TokenType>> = rest
+            .iter()
+            .map(|item| item.as_ref().ok().map(|t| t.token_type))
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                Some(TokenType::Identifier),
+                Some(TokenType::Equal),
+                Some(TokenType::Number),
+                None,
+                Some(TokenType::Number),
+                Some(TokenType::Semicolon),
+                Some(TokenType::Eof),
+            ]
+        );
+    }
+
+    // A throughput check over a few megabytes of mixed ASCII and Osmanya
+    // source. Run it with
+    // `cargo test --release lex_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn lex_benchmark() {
+        let line = "weel magac_\u{10496} = \u{201C}Soo dhowoow\u{201D} + 12.5 * (x - \u{104a3});\n";
+        let time = |megabytes: usize| {
+            let source = line.repeat(megabytes * 1_000_000 / line.len());
+            let started = std::time::Instant::now();
+            let count = Lexer::new(&source).lex().unwrap().len();
+            let elapsed = started.elapsed();
+            println!(
+                "{} MB: {} tokens in {:?} ({:.1} MB/s)",
+                megabytes,
+                count,
+                elapsed,
+                megabytes as f64 / elapsed.as_secs_f64()
+            );
+            elapsed
+        };
+
+        let small = time(1);
+        let large = time(8);
+        // Linear lexing takes about 8x as long; allow plenty of noise.
+        assert!(large < small * 24, "lexing is not linear");
+    }
+
+    fn string_value(source: &str) -> Result<String> {
+        match Lexer::new(source).lex()?.remove(0).literal {
+            Some(LiteralValue::StringValue(value)) => Ok(value),
+            literal => panic!("not a string: {:?}", literal),
+        }
+    }
+
+    #[test]
+    fn string_escapes() {
+        assert_eq!(
+            string_value(r#""a\tb\n\"c\" \\ \u{1049B}\u{e1}""#).unwrap(),
+            "a\tb\n\"c\" \\ \u{1049B}\u{e1}"
+        );
+        assert_eq!(
+            string_value("\u{201C}ka \\\u{201D}\u{201D}").unwrap(),
+            "ka \u{201D}"
+        );
+
+        let tokens = Lexer::new(r#""x\ny""#).lex().unwrap();
+        assert_eq!(tokens[0].lexeme, r#""x\ny""#);
+
+        let error = string_value(r#""\q""#).unwrap_err();
+        assert_eq!(error.to_string(), "Unknown escape sequence: '\\q'");
+        let error = string_value(r#""\u{110000}""#).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid unicode escape: '\\u{110000}'");
+        assert!(string_value(r#""\u0041""#).is_err());
+    }
+
+    #[test]
+    fn raw_and_triple_quoted_strings() {
+        assert_eq!(string_value(r#"r"C:\new\t""#).unwrap(), r"C:\new\t");
+        assert_eq!(string_value("r\u{201C}\\d+\u{201D}").unwrap(), "\\d+");
+
+        let source = "\"\"\"\n    hawl f() {\n        celi \"\\u{41}\";\n    }\n\n    \"\"\"; r";
+        let tokens = Lexer::new(source).lex().unwrap();
+        assert_eq!(
+            tokens[0].literal,
+            Some(LiteralValue::StringValue(
+                "hawl f() {\n    celi \"A\";\n}\n".to_string()
+            ))
+        );
+        assert_eq!(tokens[1].line_number, 5);
+        assert_eq!(tokens[2].token_type, TokenType::Identifier);
+
+        assert_eq!(
+            string_value("r\"\"\"\n  a\\n\n  b\"\"\"").unwrap(),
+            "a\\n\nb"
+        );
+        assert_eq!(string_value("\"\"").unwrap(), "");
+        assert!(string_value("\"\"\"open").is_err());
+    }
+
+    #[test]
+    fn numeric_literal_forms() {
+        let tokens = Lexer::new("0x1F 0b1010 0o17 6.02e23 1_000_000 2.5E-3 1e+2 0..3")
+            .lex()
+            .unwrap();
+        let values: Vec<Option<LiteralValue>> = tokens.into_iter().map(|t| t.literal).collect();
+
+        let number = |value: f32| Some(LiteralValue::FloatValue(value));
+        assert_eq!(
+            values,
+            vec![
+                number(31.0),
+                number(10.0),
+                number(15.0),
+                number(6.02e23),
+                number(1_000_000.0),
+                number(2.5e-3),
+                number(100.0),
+                number(0.0),
+                None,
+                number(3.0),
+                None,
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_numbers() {
+        let cases = [
+            ("0x;", "Expected digits after '0x'", 0, 2),
+            ("1e;", "Expected digits after '1e'", 0, 2),
+            ("weel n = 0b102;", "'2' is not a base-2 digit", 13, 1),
+            ("1__0", "A '_' separator must sit between two digits", 1, 1),
+            ("0x_1", "A '_' separator must sit between two digits", 2, 1),
+            ("1e99", "Invalid number: 1e99", 0, 4),
+        ];
+
+        for (source, message, column, length) in cases {
+            let error = Lexer::new(source).lex().unwrap_err();
+            let span = error.span.unwrap();
+            assert_eq!(
+                (error.to_string().as_str(), span.column, span.length),
+                (message, column, length),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn block_comments_nest() {
+        let source = "1 /* a /* b\n */ c */ 2 /**/ 3";
+        let tokens = Lexer::new(source).lex().unwrap();
+        let lines: Vec<(&str, usize)> = tokens
+            .iter()
+            .map(|t| (t.lexeme.as_str(), t.line_number))
+            .collect();
+        assert_eq!(lines, vec![("1", 0), ("2", 1), ("3", 1), ("", 1)]);
+
+        let error = Lexer::new("weel x;\n  /* a /* b */\n").lex().unwrap_err();
+        let span = error.span.unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Unterminated block comment opened on line 2"
+        );
+        assert_eq!((span.line, span.column, span.length), (1, 2, 2));
+    }
+
+    #[test]
+    fn doc_comments_attach_to_next_token() {
+        let source = "/// Salaan.\n///   Labaad\n//// not a doc\nhawl f() {}\n// plain\nweel x;";
+        let tokens = Lexer::new(source).lex().unwrap();
+
+        assert_eq!(tokens[0].doc.as_deref(), Some("Salaan.\n  Labaad"));
+        assert!(tokens[1..].iter().all(|t| t.doc.is_none()));
+    }
 }
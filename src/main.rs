@@ -1,35 +1,127 @@
 use std::{
-    io::{self, BufRead, Write},
+    fs::File,
+    io::{self, BufRead, IsTerminal, Write},
     process::exit,
 };
 
-use anyhow::{Error, Result};
-use environment::Environment;
+use anyhow::Result;
+use checker::Checker;
+use dialect::{Dialect, SOMALI};
+use error::{Error, Errors};
+use infer::Inference;
 use interpreter::Interpreter;
+use message::Language;
+use numerals::Numerals;
 use parser::Parser;
+use resolver::Resolver;
+use syntax::SyntaxTree;
+use trace::Phase;
+mod channel;
+mod checker;
+mod dialect;
 mod environment;
+mod error;
 mod expr;
+mod generator;
+mod infer;
 mod interpreter;
+mod iterator;
 mod lexer;
+mod message;
+mod native;
+mod numerals;
 mod parser;
+mod pattern;
+mod resolver;
+mod routine;
+mod scheduler;
+mod set;
 mod statement;
+mod syntax;
+mod trace;
 
 fn main() -> Result<()> {
-    let args: Vec<String> = std::env::args().collect();
+    let mut args: Vec<String> = std::env::args().collect();
+    let usage = format!(
+        "Usage: {} [--lang so|en] [--dialect so|en|os] [--numerals latin|osmanya] [--trace PHASES] [--trace-file PATH] [--check | --types | --syntax] [filename]",
+        args[0]
+    );
 
-    if args.len() > 2 {
-        eprintln!("Usage: {} [filename]", args[0]);
-        exit(1);
-    } else if args.len() == 2 {
-        match run_file(&args[1]) {
-            Ok(_) => (),
-            Err(e) => {
-                eprintln!("ERROR: {}", e);
+    // `--lang` overrides MALI_LANG, which overrides the Somali default.
+    let mut language = Language::from_env();
+    if let Some(name) = take_flag(&mut args, "--lang") {
+        match Language::from_name(&name) {
+            Some(chosen) => language = chosen,
+            None => {
+                eprintln!("{}", usage);
+                exit(1);
+            }
+        }
+    }
+
+    // `--dialect` sets the keywords for files without a `lahjad` pragma.
+    let mut dialect = &SOMALI;
+    if let Some(name) = take_flag(&mut args, "--dialect") {
+        match Dialect::from_name(&name) {
+            Some(chosen) => dialect = chosen,
+            None => {
+                eprintln!("{}", usage);
+                exit(1);
+            }
+        }
+    }
+
+    // `--numerals osmanya` prints numbers with Osmanya digits.
+    if let Some(name) = take_flag(&mut args, "--numerals") {
+        match Numerals::from_name(&name) {
+            Some(numerals) => numerals.set(),
+            None => {
+                eprintln!("{}", usage);
                 exit(1);
             }
         }
+    }
+
+    // `--trace parse,run` (or MALI_TRACE) writes what the chosen phases do
+    // to stderr, or to `--trace-file` (or MALI_TRACE_FILE). Naming only a
+    // file traces every phase.
+    let trace_file =
+        take_flag(&mut args, "--trace-file").or_else(|| std::env::var("MALI_TRACE_FILE").ok());
+    let trace = take_flag(&mut args, "--trace")
+        .or_else(|| std::env::var("MALI_TRACE").ok())
+        .or_else(|| trace_file.as_ref().map(|_| "all".to_string()));
+    if let Some(list) = trace {
+        let Some(phases) = Phase::parse_list(&list) else {
+            eprintln!("{}", usage);
+            exit(1);
+        };
+        let output: Box<dyn Write + Send> = match trace_file {
+            Some(path) => match File::create(&path) {
+                Ok(file) => Box::new(file),
+                Err(e) => {
+                    eprintln!("ERROR: {}: {}", path, e);
+                    exit(1);
+                }
+            },
+            None => Box::new(io::stderr()),
+        };
+        trace::enable(&phases, output);
+    }
+
+    let settings = Settings { language, dialect };
+    if args.len() == 3 && args[1] == "--check" {
+        run_file(&args[2], check_source, settings);
+    } else if args.len() == 3 && args[1] == "--types" {
+        run_file(&args[2], infer_source, settings);
+    } else if args.len() == 3 && args[1] == "--syntax" {
+        run_file(&args[2], syntax_source, settings);
+    } else if args.len() > 2 {
+        eprintln!("{}", usage);
+        exit(1);
+    } else if args.len() == 2 {
+        run_file(&args[1], run_source, settings);
     } else {
-        match run_prompt() {
+        match run_prompt(settings) {
             Ok(_) => exit(0),
             Err(e) => {
                 eprintln!("ERROR: {}", e);
@@ -41,26 +133,119 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn run_file(path: &str) -> Result<()> {
+#[derive(Clone, Copy)]
+struct Settings {
+    language: Language,
+    dialect: &'static Dialect,
+}
+
+fn take_flag(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let index = args.iter().position(|arg| arg == flag)?;
+    let value = args.get(index + 1).cloned().unwrap_or_default();
+    args.drain(index..(index + 2).min(args.len()));
+    Some(value)
+}
+
+fn run_file(path: &str, action: fn(&str, &'static Dialect) -> Result<()>, settings: Settings) {
+    let source = match std::fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("ERROR: {}", e);
+            exit(1);
+        }
+    };
+
+    let settings = Settings {
+        dialect: Dialect::for_source(&source, settings.dialect),
+        ..settings
+    };
+    if let Err(e) = action(&source, settings.dialect) {
+        eprint!("{}", report(&e, &source, path, settings));
+        exit(1);
+    }
+}
+
+fn run_source(source: &str, dialect: &'static Dialect) -> Result<()> {
     let mut interpreter = Interpreter::new();
-    let source = std::fs::read_to_string(path)?;
-    run(&mut interpreter, &source)?;
+    run(&mut interpreter, source, dialect)?;
+    Ok(())
+}
+
+// The analysis modes go through the syntax tree, which reports every lexer
+// error rather than stopping at the first.
+fn check_source(source: &str, dialect: &'static Dialect) -> Result<()> {
+    let statements = SyntaxTree::parse(source, dialect).lower()?;
+
+    Resolver::new().resolve(&statements)?;
+    Checker::new().check(&statements)?;
+
+    Ok(())
+}
+
+fn infer_source(source: &str, dialect: &'static Dialect) -> Result<()> {
+    let statements = SyntaxTree::parse(source, dialect).lower()?;
+
+    Resolver::new().resolve(&statements)?;
+    for binding in Inference::new().infer(&statements)? {
+        println!("{}", binding);
+    }
+
     Ok(())
 }
 
-fn run(interpreter: &mut Interpreter, buffer: &str) -> Result<()> {
-    let mut lexer = lexer::Lexer::new(buffer);
+fn syntax_source(source: &str, dialect: &'static Dialect) -> Result<()> {
+    let tree = SyntaxTree::parse(source, dialect);
+    debug_assert_eq!(tree.text(), source);
+    print!("{}", tree.root);
+
+    match tree.errors.is_empty() {
+        true => Ok(()),
+        false => Err(Errors(tree.errors).into()),
+    }
+}
+
+fn run(interpreter: &mut Interpreter, buffer: &str, dialect: &'static Dialect) -> Result<()> {
+    let mut lexer = lexer::Lexer::with_dialect(buffer, dialect);
     let tokens = lexer.lex()?;
 
     let mut ast = Parser::new(tokens);
     let statements = ast.parse()?;
 
+    Resolver::new().resolve(&statements)?;
+
     interpreter.interpret(statements)?;
 
     Ok(())
 }
 
-fn run_prompt() -> Result<(), String> {
+// Errors that carry a span are shown against the source they came from;
+// anything else (a missing file, say) is printed as is.
+fn report(error: &anyhow::Error, source: &str, file: &str, settings: Settings) -> String {
+    let Settings { language, dialect } = settings;
+    if let Some(errors) = error.downcast_ref::<Errors>() {
+        let mut out: String = errors
+            .0
+            .iter()
+            .map(|error| error.render(source, file, colour(), language, dialect) + "\n")
+            .collect();
+        if errors.0.len() > 1 {
+            out.push_str(&language.errors_found(errors.0.len()));
+            out.push('\n');
+        }
+        return out;
+    }
+
+    match error.downcast_ref::<Error>() {
+        Some(error) => error.render(source, file, colour(), language, dialect),
+        None => format!("ERROR: {}\n", error),
+    }
+}
+
+fn colour() -> bool {
+    io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+fn run_prompt(settings: Settings) -> Result<(), String> {
     let mut interpreter = Interpreter::new();
     loop {
         print!("> ");
@@ -81,9 +266,13 @@ fn run_prompt() -> Result<(), String> {
             Err(_) => return Err("ERROR: could not read line".to_string()),
         }
 
-        match run(&mut interpreter, &buffer) {
+        let settings = Settings {
+            dialect: Dialect::for_source(&buffer, settings.dialect),
+            ..settings
+        };
+        match run(&mut interpreter, &buffer, settings.dialect) {
             Ok(_) => (),
-            Err(msg) => println!("{}", msg),
+            Err(e) => eprint!("{}", report(&e, &buffer, "<stdin>", settings)),
         }
     }
 }
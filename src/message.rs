@@ -0,0 +1,1113 @@
+use crate::{
+    dialect::Dialect,
+    error::{ErrorCode, ErrorKind},
+    iterator::MAX_EXACT,
+    lexer::TokenType,
+};
+
+/// The language diagnostics are written in. Somali is the default; English
+/// is there for readers who need it and for tools that match on the text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    Somali,
+    English,
+}
+
+impl Language {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "so" | "somali" | "soomaali" => Some(Language::Somali),
+            "en" | "english" | "ingiriisi" => Some(Language::English),
+            _ => None,
+        }
+    }
+
+    // `MALI_LANG=en` switches to English; anything unrecognised is ignored.
+    pub fn from_env() -> Self {
+        std::env::var("MALI_LANG")
+            .ok()
+            .and_then(|name| Self::from_name(&name))
+            .unwrap_or_default()
+    }
+
+    pub fn kind(&self, kind: ErrorKind) -> &'static str {
+        match (self, kind) {
+            (Language::Somali, ErrorKind::Lex) => "khalad xaraf",
+            (Language::Somali, ErrorKind::Parse) => "khalad naxwe",
+            (Language::Somali, ErrorKind::Resolve) => "khalad magac",
+            (Language::Somali, ErrorKind::Check) => "khalad nooc",
+            (Language::Somali, ErrorKind::Runtime) => "khalad socod",
+            (Language::English, ErrorKind::Lex) => "lex error",
+            (Language::English, ErrorKind::Parse) => "parse error",
+            (Language::English, ErrorKind::Resolve) => "resolve error",
+            (Language::English, ErrorKind::Check) => "type error",
+            (Language::English, ErrorKind::Runtime) => "runtime error",
+        }
+    }
+
+    pub fn help_label(&self) -> &'static str {
+        match self {
+            Language::Somali => "caawimo",
+            Language::English => "help",
+        }
+    }
+
+    pub fn errors_found(&self, count: usize) -> String {
+        match self {
+            Language::Somali => format!("{} khaladaad ayaa la helay", count),
+            Language::English => format!("{} errors found", count),
+        }
+    }
+
+    // Notes that mention keywords hold a `{}` for each, filled in with the
+    // active dialect's spelling.
+    pub fn help(&self, code: ErrorCode, dialect: &Dialect) -> Option<String> {
+        use ErrorCode::*;
+        use Language::*;
+
+        let (note, keywords): (&str, &[TokenType]) = match (self, code) {
+            (Somali, UnexpectedCharacter) => ("ka saar xarafka ama geli qoraal gudihiis", &[]),
+            (Somali, UnterminatedString) => ("ku dar '\"' si aad u xirto qoraalka", &[]),
+            (Somali, InvalidNumber) => (
+                "tirooyinku waxay u qormaan sida 42, 1_000, 6.02e23, 0x1F, 0b1010 ama 0o17",
+                &[],
+            ),
+            (Somali, InvalidEscape) => (
+                "isticmaal \\n, \\t, \\\", \\\\ ama \\u{..}, ama qoraal cayriin r\"...\"",
+                &[],
+            ),
+            (Somali, UnterminatedComment) => (
+                "ku xir '*/'; faalladu way is-gashaa, markaa '/*' kasta wuxuu u baahan yahay '*/'",
+                &[],
+            ),
+            (Somali, ExpectedToken) => ("halkan ku dar calaamadda maqan", &[]),
+            (Somali, InvalidPattern) => ("qaabka kala-furiddu wuxuu u baahan yahay qiime", &[]),
+            (Somali, AssignToConstant) => (
+                "ku caddee '{}' haddii uu isbeddeli doono",
+                &[TokenType::Var],
+            ),
+            (Somali, UndefinedVariable) => (
+                "ku caddee '{}' ka hor intaadan isticmaalin",
+                &[TokenType::Var],
+            ),
+            (Somali, DivisionByZero) => ("marka hore hubi in qaybiyuhu aanu eber ahayn", &[]),
+            (Somali, IndexOutOfRange) => (
+                "tusmooyinku waxay ka bilaabmaan 0 waxayna ku dhammaadaan dhererka ka hor",
+                &[],
+            ),
+            (Somali, ArgumentCount) => ("eeg halbeegyada ku qoran qeexidda hawsha", &[]),
+            (Somali, NotIterable) => (
+                "liisas, xadad, kooxo, ururro iyo soo-saareyaal oo keliya ayaa lagu wareegi karaa",
+                &[],
+            ),
+            (Somali, Deadlock) => (
+                "'{}' kasta wuxuu u baahan yahay hawl '{}' ku samaynaysa isla kanaalka",
+                &[TokenType::Receive, TokenType::Send],
+            ),
+            (Somali, TypeMismatch) => (
+                "beddel qiimaha ama nooca lagu qoray si ay isugu ekaadaan",
+                &[],
+            ),
+            (Somali, UnknownType) => (
+                "noocyada dhisan waa Tiro, Qoraal, Xaqiiq, Waxba, Liis, Koox, Urur, Hawl iyo Wax; \
+                 noocyada lagu caddeeyay '{}' sidoo kale waa la isticmaali karaa",
+                &[TokenType::Enum],
+            ),
+            (English, UnexpectedCharacter) => {
+                ("remove the character or put it inside a string", &[])
+            }
+            (English, UnterminatedString) => ("add a closing '\"' to end the string", &[]),
+            (English, InvalidNumber) => (
+                "numbers are written like 42, 1_000, 6.02e23, 0x1F, 0b1010 or 0o17",
+                &[],
+            ),
+            (English, InvalidEscape) => (
+                "use \\n, \\t, \\\", \\\\ or \\u{..}, or a raw string r\"...\"",
+                &[],
+            ),
+            (English, UnterminatedComment) => (
+                "close it with '*/'; comments nest, so every '/*' needs its own '*/'",
+                &[],
+            ),
+            (English, ExpectedToken) => ("insert the missing token here", &[]),
+            (English, InvalidPattern) => ("a destructuring pattern needs an initializer", &[]),
+            (English, AssignToConstant) => (
+                "declare it with '{}' if it needs to change",
+                &[TokenType::Var],
+            ),
+            (English, UndefinedVariable) => {
+                ("declare it with '{}' before using it", &[TokenType::Var])
+            }
+            (English, DivisionByZero) => ("check that the divisor is not zero first", &[]),
+            (English, IndexOutOfRange) => ("indexes start at 0 and stop before the length", &[]),
+            (English, ArgumentCount) => ("check the parameters in the function's definition", &[]),
+            (English, NotIterable) => (
+                "only lists, ranges, tuples, sets and generators can be looped over",
+                &[],
+            ),
+            (English, Deadlock) => (
+                "every '{}' needs a task that will '{}' on the same channel",
+                &[TokenType::Receive, TokenType::Send],
+            ),
+            (English, TypeMismatch) => {
+                ("change the value or the annotation so that they agree", &[])
+            }
+            (English, UnknownType) => (
+                "the built-in types are Tiro, Qoraal, Xaqiiq, Waxba, Liis, Koox, Urur, Hawl and \
+                 Wax; enums declared with '{}' work too",
+                &[TokenType::Enum],
+            ),
+            _ => return None,
+        };
+
+        let mut text = note.to_string();
+        for keyword in keywords {
+            text = text.replacen("{}", dialect.spelling(*keyword), 1);
+        }
+        Some(text)
+    }
+}
+
+/// Where in the grammar a token was expected, for `Message::Expected`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Place {
+    AfterArguments,
+    AfterChannel,
+    AfterConstantDeclaration,
+    AfterConstantName,
+    AfterEnumName,
+    AfterEnumVariants,
+    AfterExpression,
+    AfterFunctionBody,
+    AfterFunctionName,
+    AfterIndex,
+    AfterListElements,
+    AfterListPattern,
+    AfterMatchArms,
+    AfterObjectPattern,
+    AfterParameters,
+    AfterPrint,
+    AfterReturnValue,
+    AfterSpawnedCall,
+    AfterTupleElements,
+    AfterVariableDeclaration,
+    AfterVariantFields,
+    AfterYieldedValue,
+    BeforeEnumVariants,
+    BeforeFunctionBody,
+    BeforeMatchArms,
+    BlockEnd,
+    BlockStart,
+    InConditional,
+}
+
+impl Place {
+    fn text(&self, language: Language) -> &'static str {
+        use Language::*;
+        use Place::*;
+
+        match (language, self) {
+            (Somali, AfterArguments) => "ka dib doodaha",
+            (Somali, AfterChannel) => "ka dib kanaalka",
+            (Somali, AfterConstantDeclaration) => "ka dib caddaynta joogtada",
+            (Somali, AfterConstantName) => "ka dib magaca joogtada",
+            (Somali, AfterEnumName) => "ka dib magaca nooca",
+            (Somali, AfterEnumVariants) => "ka dib faracyada nooca",
+            (Somali, AfterExpression) => "ka dib tibaaxda",
+            (Somali, AfterFunctionBody) => "ka dib jirka hawsha",
+            (Somali, AfterFunctionName) => "ka dib magaca hawsha",
+            (Somali, AfterIndex) => "ka dib tusmada",
+            (Somali, AfterListElements) => "ka dib xubnaha liiska",
+            (Somali, AfterListPattern) => "ka dib qaabka liiska",
+            (Somali, AfterMatchArms) => "ka dib xaaladaha dooro",
+            (Somali, AfterObjectPattern) => "ka dib qaabka shayga",
+            (Somali, AfterParameters) => "ka dib halbeegyada",
+            (Somali, AfterPrint) => "ka dib amarka daabac",
+            (Somali, AfterReturnValue) => "ka dib qiimaha la celinayo",
+            (Somali, AfterSpawnedCall) => "ka dib wicitaanka la bilaabay",
+            (Somali, AfterTupleElements) => "ka dib xubnaha kooxda",
+            (Somali, AfterVariableDeclaration) => "ka dib caddaynta doorsoomaha",
+            (Somali, AfterVariantFields) => "ka dib beeraha faraca",
+            (Somali, AfterYieldedValue) => "ka dib qiimaha la bixinayo",
+            (Somali, BeforeEnumVariants) => "ka hor faracyada nooca",
+            (Somali, BeforeFunctionBody) => "ka hor jirka hawsha",
+            (Somali, BeforeMatchArms) => "ka hor xaaladaha dooro",
+            (Somali, BlockEnd) => "si loo xiro baloogga",
+            (Somali, BlockStart) => "si loo furo baloogga",
+            (Somali, InConditional) => "gudaha tibaaxda shuruudda",
+            (English, AfterArguments) => "after arguments",
+            (English, AfterChannel) => "after channel",
+            (English, AfterConstantDeclaration) => "after constant declaration",
+            (English, AfterConstantName) => "after constant name",
+            (English, AfterEnumName) => "after enum name",
+            (English, AfterEnumVariants) => "after enum variants",
+            (English, AfterExpression) => "after expression",
+            (English, AfterFunctionBody) => "after function body",
+            (English, AfterFunctionName) => "after function name",
+            (English, AfterIndex) => "after index",
+            (English, AfterListElements) => "after list elements",
+            (English, AfterListPattern) => "after list pattern",
+            (English, AfterMatchArms) => "after match arms",
+            (English, AfterObjectPattern) => "after object pattern",
+            (English, AfterParameters) => "after parameters",
+            (English, AfterPrint) => "after print statement",
+            (English, AfterReturnValue) => "after return value",
+            (English, AfterSpawnedCall) => "after spawned call",
+            (English, AfterTupleElements) => "after tuple elements",
+            (English, AfterVariableDeclaration) => "after variable declaration",
+            (English, AfterVariantFields) => "after variant fields",
+            (English, AfterYieldedValue) => "after yielded value",
+            (English, BeforeEnumVariants) => "before enum variants",
+            (English, BeforeFunctionBody) => "before function body",
+            (English, BeforeMatchArms) => "before match arms",
+            (English, BlockEnd) => "to end block",
+            (English, BlockStart) => "to start block",
+            (English, InConditional) => "in conditional expression",
+        }
+    }
+}
+
+/// The kind of name the parser wanted, for `Message::ExpectedName`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Name {
+    Constant,
+    Enum,
+    Field,
+    Function,
+    LoopVariable,
+    Property,
+    Rest,
+    Type,
+    Variable,
+    VariableOrPattern,
+    Variant,
+}
+
+impl Name {
+    fn text(&self, language: Language) -> &'static str {
+        use Language::*;
+        use Name::*;
+
+        match (language, self) {
+            (Somali, Constant) => "magaca joogtada",
+            (Somali, Enum) => "magaca nooca",
+            (Somali, Field) => "magaca beerta",
+            (Somali, Function) => "magaca hawsha",
+            (Somali, LoopVariable) => "magaca doorsoomaha wareegga",
+            (Somali, Property) => "magaca astaanta ka dib '.'",
+            (Somali, Rest) => "magac ka dib '...'",
+            (Somali, Type) => "magaca nooca xogta ka dib ':'",
+            (Somali, Variable) => "magaca doorsoomaha",
+            (Somali, VariableOrPattern) => "magaca doorsoomaha ama qaab",
+            (Somali, Variant) => "magaca faraca",
+            (English, Constant) => "constant name",
+            (English, Enum) => "enum name",
+            (English, Field) => "field name",
+            (English, Function) => "function name",
+            (English, LoopVariable) => "loop variable name",
+            (English, Property) => "property name after '.'",
+            (English, Rest) => "name after '...'",
+            (English, Type) => "type name after ':'",
+            (English, Variable) => "variable name",
+            (English, VariableOrPattern) => "variable name or pattern",
+            (English, Variant) => "variant name",
+        }
+    }
+}
+
+/// Binary operations whose operands can have the wrong type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Compare,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collection {
+    String,
+    List,
+    Tuple,
+}
+
+impl Collection {
+    fn text(&self, language: Language) -> &'static str {
+        match (language, self) {
+            (Language::Somali, Collection::String) => "qoraal",
+            (Language::Somali, Collection::List) => "liis",
+            (Language::Somali, Collection::Tuple) => "koox",
+            (Language::English, Collection::String) => "string",
+            (Language::English, Collection::List) => "list",
+            (Language::English, Collection::Tuple) => "tuple",
+        }
+    }
+}
+
+/// What a task was doing when every task stopped, for `Message::Deadlock`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskState {
+    Sending,
+    Receiving,
+    Runnable,
+}
+
+impl TaskState {
+    fn text(&self, language: Language) -> &'static str {
+        match (language, self) {
+            (Language::Somali, TaskState::Sending) => "sugaysa inay dirto",
+            (Language::Somali, TaskState::Receiving) => "sugaysa inay hesho",
+            (Language::Somali, TaskState::Runnable) => "diyaar",
+            (Language::English, TaskState::Sending) => "waiting to send",
+            (Language::English, TaskState::Receiving) => "waiting to receive",
+            (Language::English, TaskState::Runnable) => "runnable",
+        }
+    }
+}
+
+/// Every diagnostic the lexer, parser, resolver and interpreter can raise.
+/// Values are formatted into strings where the error is raised, so that the
+/// catalogue below only has to place them in a sentence.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    UnexpectedCharacter(char),
+    UnterminatedString,
+    InvalidNumber(String),
+    MissingDigits(String),
+    InvalidDigit(char, u32),
+    MisplacedSeparator,
+    UnterminatedComment {
+        line: usize,
+    },
+    UnknownEscape(char),
+    InvalidUnicodeEscape(String),
+
+    UnexpectedToken(String),
+    UnexpectedEof,
+    Expected(&'static str, Place),
+    ExpectedName(Name),
+    ExpectedKeyword(TokenType),
+    InvalidAssignmentTarget,
+    PatternNeedsInitializer,
+    SpawnNeedsCall,
+
+    AssignToConstant {
+        name: String,
+        line: usize,
+    },
+    UndefinedVariable(String),
+    ReadProperty {
+        name: String,
+        value: String,
+    },
+    SetProperty(String),
+    GeneratorProperty {
+        generator: String,
+        name: String,
+    },
+    VariantField {
+        variant: String,
+        field: String,
+    },
+    UndefinedVariant {
+        name: String,
+        enum_name: String,
+        valid: String,
+    },
+
+    InvalidOperator(String),
+    CannotNegate(String),
+    CannotApply {
+        operation: Operation,
+        left: String,
+        right: String,
+    },
+    CannotMakeRange {
+        from: String,
+        to: String,
+    },
+    RangeTooLarge {
+        from: String,
+        to: String,
+    },
+    CannotIndex {
+        object: String,
+        index: String,
+    },
+    IndexOutOfRange {
+        index: String,
+        collection: Collection,
+        length: usize,
+    },
+    DivisionByZero,
+    CannotSum(String),
+    ChannelCapacity(String),
+    NotAChannel(String),
+    NotHashable(String),
+    ExpectedSet(String),
+    CannotCallNext(String),
+
+    CannotCall(String),
+    CannotSpawn(String),
+    CannotSpawnGenerator(String),
+    NativeArity {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    FunctionArity {
+        name: String,
+        required: usize,
+        got: usize,
+    },
+    VariantArity {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    NotIterable(String),
+
+    CannotDestructureList(String),
+    CannotDestructureObject(String),
+    ListPatternTooShort {
+        expected: usize,
+        got: usize,
+    },
+    ListPatternTooLong(usize),
+    MissingDestructuredField {
+        value: String,
+        field: String,
+    },
+    NotAnEnum(String),
+    NoMatchingArm(String),
+
+    InitializerType {
+        name: String,
+        declared: String,
+        actual: String,
+    },
+    AssignmentType {
+        name: String,
+        declared: String,
+        actual: String,
+    },
+    ReturnType {
+        function: String,
+        expected: String,
+        actual: String,
+    },
+    ArgumentType {
+        index: usize,
+        function: String,
+        expected: String,
+        actual: String,
+    },
+    DefaultType {
+        parameter: String,
+        function: String,
+        expected: String,
+        actual: String,
+    },
+    AnnotationType {
+        expected: String,
+        actual: String,
+    },
+    TooManyArguments {
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    UnknownType(String),
+
+    ReturnOutsideFunction,
+    YieldOutsideFunction,
+    NestedTask,
+    GeneratorRunning(String),
+    Deadlock(Vec<(String, TaskState)>),
+    TaskFailed {
+        task: String,
+        error: Box<Message>,
+    },
+}
+
+impl Message {
+    pub fn code(&self) -> ErrorCode {
+        use Message::*;
+
+        match self {
+            UnexpectedCharacter(_) => ErrorCode::UnexpectedCharacter,
+            UnterminatedString => ErrorCode::UnterminatedString,
+            InvalidNumber(_) | MissingDigits(_) | InvalidDigit(..) | MisplacedSeparator => {
+                ErrorCode::InvalidNumber
+            }
+            UnknownEscape(_) | InvalidUnicodeEscape(_) => ErrorCode::InvalidEscape,
+            UnterminatedComment { .. } => ErrorCode::UnterminatedComment,
+            UnexpectedToken(_) | UnexpectedEof | InvalidAssignmentTarget => {
+                ErrorCode::UnexpectedToken
+            }
+            Expected(..) | ExpectedName(_) | ExpectedKeyword(_) => ErrorCode::ExpectedToken,
+            PatternNeedsInitializer => ErrorCode::InvalidPattern,
+            SpawnNeedsCall => ErrorCode::InvalidStatement,
+            AssignToConstant { .. } => ErrorCode::AssignToConstant,
+            UndefinedVariable(_) => ErrorCode::UndefinedVariable,
+            ReadProperty { .. }
+            | SetProperty(_)
+            | GeneratorProperty { .. }
+            | VariantField { .. }
+            | UndefinedVariant { .. } => ErrorCode::UndefinedProperty,
+            InvalidOperator(_)
+            | CannotNegate(_)
+            | CannotApply { .. }
+            | CannotMakeRange { .. }
+            | RangeTooLarge { .. }
+            | CannotIndex { .. }
+            | CannotSum(_)
+            | ChannelCapacity(_)
+            | NotAChannel(_)
+            | NotHashable(_)
+            | ExpectedSet(_)
+            | CannotCallNext(_) => ErrorCode::InvalidOperand,
+            IndexOutOfRange { .. } => ErrorCode::IndexOutOfRange,
+            DivisionByZero => ErrorCode::DivisionByZero,
+            CannotCall(_) | CannotSpawn(_) | CannotSpawnGenerator(_) => ErrorCode::NotCallable,
+            NativeArity { .. }
+            | FunctionArity { .. }
+            | VariantArity { .. }
+            | TooManyArguments { .. } => ErrorCode::ArgumentCount,
+            NotIterable(_) => ErrorCode::NotIterable,
+            CannotDestructureList(_)
+            | CannotDestructureObject(_)
+            | ListPatternTooShort { .. }
+            | ListPatternTooLong(_)
+            | MissingDestructuredField { .. }
+            | NotAnEnum(_)
+            | NoMatchingArm(_) => ErrorCode::PatternMismatch,
+            InitializerType { .. }
+            | AssignmentType { .. }
+            | ReturnType { .. }
+            | ArgumentType { .. }
+            | DefaultType { .. }
+            | AnnotationType { .. } => ErrorCode::TypeMismatch,
+            UnknownType(_) => ErrorCode::UnknownType,
+            ReturnOutsideFunction | YieldOutsideFunction | NestedTask | GeneratorRunning(_) => {
+                ErrorCode::ControlFlow
+            }
+            Deadlock(_) => ErrorCode::Deadlock,
+            TaskFailed { error, .. } => error.code(),
+        }
+    }
+
+    /// The message in `language`, quoting keywords as `dialect` spells them.
+    pub fn text(&self, language: Language, dialect: &Dialect) -> String {
+        match language {
+            Language::Somali => self.somali(dialect),
+            Language::English => self.english(dialect),
+        }
+    }
+
+    fn somali(&self, dialect: &Dialect) -> String {
+        use Message::*;
+
+        match self {
+            UnexpectedCharacter(ch) => format!("Xaraf aan la filayn: {}", ch),
+            UnterminatedString => "Qoraalka lama xirin".to_string(),
+            InvalidNumber(text) => format!("Tiro khaldan: {}", text),
+            MissingDigits(text) => format!("Tirooyin ayaa ka maqan '{}' kadib", text),
+            InvalidDigit(c, radix) => format!("'{}' ma aha tiro ku jirta salka {}", c, radix),
+            MisplacedSeparator => "Kala-sooca '_' waa inuu u dhexeeyaa laba tiro".to_string(),
+            UnterminatedComment { line } => {
+                format!("Faallada ka bilaabatay sadarka {} lama xirin", line)
+            }
+            UnknownEscape(c) => format!("Xarf-baxsasho aan la aqoon: '\\{}'", c),
+            InvalidUnicodeEscape(text) => format!("Xarf-baxsasho Unicode oo khaldan: '{}'", text),
+            UnexpectedToken(lexeme) => format!("Calaamad aan la filayn '{}'", lexeme),
+            UnexpectedEof => "Faylku wuu dhammaaday iyadoo wax kale la filayay".to_string(),
+            Expected(token, place) => {
+                format!(
+                    "'{}' ayaa la filayay {}",
+                    token,
+                    place.text(Language::Somali)
+                )
+            }
+            ExpectedName(name) => format!("Waxaa la filayay {}", name.text(Language::Somali)),
+            ExpectedKeyword(keyword) => {
+                format!("Ereyga '{}' ayaa la filayay", dialect.spelling(*keyword))
+            }
+            InvalidAssignmentTarget => "Halkan wax laguma qiimeyn karo".to_string(),
+            PatternNeedsInitializer => "'=' ayaa la filayay ka dib qaabka kala-furidda".to_string(),
+            SpawnNeedsCall => format!(
+                "Wicitaan hawleed ayaa la filayay ka dib '{}'",
+                dialect.spelling(TokenType::Spawn)
+            ),
+            AssignToConstant { name, line } => format!(
+                "Joogtada '{}' ee sadarka {} lagu caddeeyay lama beddeli karo",
+                name, line
+            ),
+            UndefinedVariable(name) => format!("Doorsoomaha {:?} lama qeexin", name),
+            ReadProperty { name, value } => {
+                format!("Astaanta '{}' lagama akhrin karo {}", name, value)
+            }
+            SetProperty(name) => format!("Ururku ma laha astaanta '{}'", name),
+            GeneratorProperty { generator, name } => {
+                format!("Soo-saaraha '{}' ma laha astaanta '{}'", generator, name)
+            }
+            VariantField { variant, field } => {
+                format!("Faraca '{}' ma laha beerta '{}'", variant, field)
+            }
+            UndefinedVariant {
+                name,
+                enum_name,
+                valid,
+            } => format!(
+                "Faraca '{}' kuma jiro nooca '{}'. Faracyada jira waa: {}",
+                name, enum_name, valid
+            ),
+            InvalidOperator(operator) => format!("Hawlgalka {} lama qiimeyn karo", operator),
+            CannotNegate(value) => format!("{} lama taban karo", value),
+            CannotApply {
+                operation,
+                left,
+                right,
+            } => {
+                let verb = match operation {
+                    Operation::Add => "isku dari",
+                    Operation::Subtract => "kala jari",
+                    Operation::Multiply => "isku dhufan",
+                    Operation::Divide => "qaybin",
+                    Operation::Compare => "barbar dhigi",
+                };
+                format!("{} iyo {} lama {} karo", left, right, verb)
+            }
+            CannotMakeRange { from, to } => {
+                format!("Xad lagama samayn karo {} ilaa {}", from, to)
+            }
+            RangeTooLarge { from, to } => {
+                format!(
+                    "Xadka {} ilaa {} aad buu u weyn yahay; xuduudaha waa inay u dhexeeyaan -{} iyo {}",
+                    from, to, MAX_EXACT, MAX_EXACT
+                )
+            }
+            CannotIndex { object, index } => format!("{} laguma tusmeyn karo {}", object, index),
+            IndexOutOfRange {
+                index,
+                collection,
+                length,
+            } => format!(
+                "Tusmada {} way ka baxsan tahay {} dhererkiisu yahay {}",
+                index,
+                collection.text(Language::Somali),
+                length
+            ),
+            DivisionByZero => "Eber wax laguma qaybin karo".to_string(),
+            CannotSum(value) => format!("{} laguma dari karo wadarta", value),
+            ChannelCapacity(value) => format!(
+                "Cabbirka kanaalku waa inuu noqdaa tiro dhan, waxaa la helay {}",
+                value
+            ),
+            NotAChannel(value) => format!("{} ma aha kanaal", value),
+            NotHashable(value) => format!("{} ma noqon karo xubin urur", value),
+            ExpectedSet(value) => format!("Urur ayaa la filayay laakiin waxaa la helay {}", value),
+            CannotCallNext(value) => format!("'xiga' laguma wici karo {}", value),
+            CannotCall(value) => format!("{} lama wici karo", value),
+            CannotSpawn(value) => format!("{} looma bilaabi karo hawl ahaan", value),
+            CannotSpawnGenerator(name) => {
+                format!("Soo-saaraha '{}' looma bilaabi karo hawl ahaan", name)
+            }
+            NativeArity {
+                name,
+                expected,
+                got,
+            } => format!(
+                "'{}' waxay rabtaa {} dood laakiin waxay heshay {}",
+                name, expected, got
+            ),
+            FunctionArity {
+                name,
+                required,
+                got,
+            } => format!(
+                "Hawsha '{}' waxay rabtaa ugu yaraan {} dood laakiin waxay heshay {}",
+                name, required, got
+            ),
+            VariantArity {
+                name,
+                expected,
+                got,
+            } => format!(
+                "Faraca '{}' wuxuu rabaa {} dood laakiin wuxuu helay {}",
+                name, expected, got
+            ),
+            NotIterable(value) => format!("{} laguma wareegi karo", value),
+            CannotDestructureList(value) => format!("{} laguma kala furi karo qaab liis", value),
+            CannotDestructureObject(value) => {
+                format!("{} laguma kala furi karo qaab shay", value)
+            }
+            ListPatternTooShort { expected, got } => format!(
+                "Qaabka liisku wuxuu rabaa ugu yaraan {} xubnood laakiin wuxuu helay {}",
+                expected, got
+            ),
+            ListPatternTooLong(most) => format!(
+                "Qaabka liisku wuxuu rabaa ugu badnaan {} xubnood laakiin wuxuu helay wax ka badan",
+                most
+            ),
+            MissingDestructuredField { value, field } => {
+                format!("{} ma laha beerta '{}' ee la kala furayo", value, field)
+            }
+            NotAnEnum(value) => format!("{} ma aha nooc", value),
+            NoMatchingArm(value) => format!(
+                "{} kuma habboona xaalad ka mid ah '{}'",
+                value,
+                dialect.spelling(TokenType::Match)
+            ),
+            InitializerType {
+                name,
+                declared,
+                actual,
+            } => format!(
+                "'{}' oo nooceedu yahay {} laguma bilaabi karo {}",
+                name, declared, actual
+            ),
+            AssignmentType {
+                name,
+                declared,
+                actual,
+            } => format!(
+                "{} looma dhiibi karo '{}' oo nooceedu yahay {}",
+                actual, name, declared
+            ),
+            ReturnType {
+                function,
+                expected,
+                actual,
+            } => format!(
+                "'{}' waxay soo celisaa {} laakiin waxaa la helay {}",
+                function, expected, actual
+            ),
+            ArgumentType {
+                index,
+                function,
+                expected,
+                actual,
+            } => format!(
+                "Doodda {} ee '{}' waxay filaysaa {} laakiin waxaa la helay {}",
+                index, function, expected, actual
+            ),
+            DefaultType {
+                parameter,
+                function,
+                expected,
+                actual,
+            } => format!(
+                "Qiimaha caadiga ah ee halbeegga '{}' ee '{}' wuxuu filayaa {} laakiin waxaa la helay {}",
+                parameter, function, expected, actual
+            ),
+            AnnotationType { expected, actual } => format!(
+                "Nooca lagu qoray wuxuu filayaa {} laakiin waxaa la helay {}",
+                expected, actual
+            ),
+            TooManyArguments {
+                name,
+                expected,
+                got,
+            } => format!(
+                "Hawsha '{}' waxay rabtaa ugu badnaan {} dood laakiin waxay heshay {}",
+                name, expected, got
+            ),
+            UnknownType(name) => format!("Nooc aan la aqoon '{}'", name),
+            ReturnOutsideFunction => "Wax lagama celin karo heerka sare ee barnaamijka".to_string(),
+            YieldOutsideFunction => "Wax lama bixin karo meel ka baxsan hawl".to_string(),
+            NestedTask => format!(
+                "'{}', '{}' iyo '{}' waxay sugi karaan ama bilaabi karaan \
+                 oo keliya si toos ah barnaamijka ama jirka hawsha",
+                dialect.spelling(TokenType::Spawn),
+                dialect.spelling(TokenType::Send),
+                dialect.spelling(TokenType::Receive)
+            ),
+            GeneratorRunning(name) => format!("Soo-saaraha '{}' horey ayuu u socdaa", name),
+            Deadlock(tasks) => format!(
+                "Xannibaad: hawl kasta way xiran tahay: {}",
+                tasks_text(tasks, Language::Somali)
+            ),
+            TaskFailed { task, error } => {
+                format!("Hawsha {} way fashilantay: {}", task, error.somali(dialect))
+            }
+        }
+    }
+
+    fn english(&self, dialect: &Dialect) -> String {
+        use Message::*;
+
+        match self {
+            UnexpectedCharacter(ch) => format!("Unexpected character: {}", ch),
+            UnterminatedString => "Unterminated string".to_string(),
+            InvalidNumber(text) => format!("Invalid number: {}", text),
+            MissingDigits(text) => format!("Expected digits after '{}'", text),
+            InvalidDigit(c, radix) => format!("'{}' is not a base-{} digit", c, radix),
+            MisplacedSeparator => "A '_' separator must sit between two digits".to_string(),
+            UnterminatedComment { line } => {
+                format!("Unterminated block comment opened on line {}", line)
+            }
+            UnknownEscape(c) => format!("Unknown escape sequence: '\\{}'", c),
+            InvalidUnicodeEscape(text) => format!("Invalid unicode escape: '{}'", text),
+            UnexpectedToken(lexeme) => format!("Unexpected token '{}'", lexeme),
+            UnexpectedEof => "Unexpected end of file".to_string(),
+            Expected(token, place) => {
+                format!("Expected '{}' {}", token, place.text(Language::English))
+            }
+            ExpectedName(name) => format!("Expected {}", name.text(Language::English)),
+            ExpectedKeyword(keyword) => {
+                format!("Expected '{}' keyword", dialect.spelling(*keyword))
+            }
+            InvalidAssignmentTarget => "Invalid assignment target".to_string(),
+            PatternNeedsInitializer => "Expected '=' after destructuring pattern".to_string(),
+            SpawnNeedsCall => format!(
+                "Expected a function call after '{}'",
+                dialect.spelling(TokenType::Spawn)
+            ),
+            AssignToConstant { name, line } => format!(
+                "Cannot assign to constant '{}' declared on line {}",
+                name, line
+            ),
+            UndefinedVariable(name) => format!("Undefined variable {:?}", name),
+            ReadProperty { name, value } => {
+                format!("Cannot read property '{}' of {}", name, value)
+            }
+            SetProperty(name) => format!("Set has no property '{}'", name),
+            GeneratorProperty { generator, name } => {
+                format!("Generator '{}' has no property '{}'", generator, name)
+            }
+            VariantField { variant, field } => {
+                format!("Variant '{}' has no field '{}'", variant, field)
+            }
+            UndefinedVariant {
+                name,
+                enum_name,
+                valid,
+            } => format!(
+                "Undefined variant '{}' for enum '{}'. Valid variants are: {}",
+                name, enum_name, valid
+            ),
+            InvalidOperator(operator) => format!("Cannot evaluate operator {}", operator),
+            CannotNegate(value) => format!("Cannot negate {}", value),
+            CannotApply {
+                operation,
+                left,
+                right,
+            } => {
+                let verb = match operation {
+                    Operation::Add => "add",
+                    Operation::Subtract => "subtract",
+                    Operation::Multiply => "multiply",
+                    Operation::Divide => "divide",
+                    Operation::Compare => "compare",
+                };
+                format!("Cannot {} {} and {}", verb, left, right)
+            }
+            CannotMakeRange { from, to } => {
+                format!("Cannot make a range from {} to {}", from, to)
+            }
+            RangeTooLarge { from, to } => format!(
+                "Range from {} to {} is too large; bounds must lie between -{} and {}",
+                from, to, MAX_EXACT, MAX_EXACT
+            ),
+            CannotIndex { object, index } => format!("Cannot index {} with {}", object, index),
+            IndexOutOfRange {
+                index,
+                collection,
+                length,
+            } => format!(
+                "Index {} is out of range for a {} of length {}",
+                index,
+                collection.text(Language::English),
+                length
+            ),
+            DivisionByZero => "Cannot divide by zero".to_string(),
+            CannotSum(value) => format!("Cannot add {} to a sum", value),
+            ChannelCapacity(value) => {
+                format!("Channel capacity must be a whole number, got {}", value)
+            }
+            NotAChannel(value) => format!("{} is not a channel", value),
+            NotHashable(value) => format!("{} cannot be a set member", value),
+            ExpectedSet(value) => format!("Expected a set but got {}", value),
+            CannotCallNext(value) => format!("Cannot call 'xiga' on {}", value),
+            CannotCall(value) => format!("Cannot call {}", value),
+            CannotSpawn(value) => format!("Cannot spawn {} as a task", value),
+            CannotSpawnGenerator(name) => {
+                format!("Cannot spawn generator '{}' as a task", name)
+            }
+            NativeArity {
+                name,
+                expected,
+                got,
+            } => format!("'{}' expects {} arguments but got {}", name, expected, got),
+            FunctionArity {
+                name,
+                required,
+                got,
+            } => format!(
+                "Function '{}' expects at least {} arguments but got {}",
+                name, required, got
+            ),
+            VariantArity {
+                name,
+                expected,
+                got,
+            } => format!(
+                "Variant '{}' expects {} arguments but got {}",
+                name, expected, got
+            ),
+            NotIterable(value) => format!("{} is not iterable", value),
+            CannotDestructureList(value) => {
+                format!("Cannot destructure {} with a list pattern", value)
+            }
+            CannotDestructureObject(value) => {
+                format!("Cannot destructure {} with an object pattern", value)
+            }
+            ListPatternTooShort { expected, got } => format!(
+                "List pattern expects at least {} elements but got {}",
+                expected, got
+            ),
+            ListPatternTooLong(most) => format!(
+                "List pattern expects at most {} elements but got more",
+                most
+            ),
+            MissingDestructuredField { value, field } => {
+                format!("{} has no field '{}' to destructure", value, field)
+            }
+            NotAnEnum(value) => format!("{} is not an enum", value),
+            NoMatchingArm(value) => format!(
+                "No arm of '{}' matches {}",
+                dialect.spelling(TokenType::Match),
+                value
+            ),
+            InitializerType {
+                name,
+                declared,
+                actual,
+            } => format!(
+                "Cannot initialise '{}' of type {} with {}",
+                name, declared, actual
+            ),
+            AssignmentType {
+                name,
+                declared,
+                actual,
+            } => format!(
+                "Cannot assign {} to '{}' of type {}",
+                actual, name, declared
+            ),
+            ReturnType {
+                function,
+                expected,
+                actual,
+            } => format!("'{}' returns {} but got {}", function, expected, actual),
+            ArgumentType {
+                index,
+                function,
+                expected,
+                actual,
+            } => format!(
+                "Argument {} of '{}' expects {} but got {}",
+                index, function, expected, actual
+            ),
+            DefaultType {
+                parameter,
+                function,
+                expected,
+                actual,
+            } => format!(
+                "Default for parameter '{}' of '{}' expects {} but got {}",
+                parameter, function, expected, actual
+            ),
+            AnnotationType { expected, actual } => {
+                format!("Type annotation expects {} but got {}", expected, actual)
+            }
+            TooManyArguments {
+                name,
+                expected,
+                got,
+            } => format!(
+                "Function '{}' expects at most {} arguments but got {}",
+                name, expected, got
+            ),
+            UnknownType(name) => format!("Unknown type '{}'", name),
+            ReturnOutsideFunction => "Cannot return from top-level code".to_string(),
+            YieldOutsideFunction => "Cannot yield outside of a function".to_string(),
+            NestedTask => format!(
+                "'{}', '{}' and '{}' can only wait or spawn directly \
+                 in the program or a task body",
+                dialect.spelling(TokenType::Spawn),
+                dialect.spelling(TokenType::Send),
+                dialect.spelling(TokenType::Receive)
+            ),
+            GeneratorRunning(name) => format!("Generator '{}' is already running", name),
+            Deadlock(tasks) => format!(
+                "Deadlock: every task is blocked: {}",
+                tasks_text(tasks, Language::English)
+            ),
+            TaskFailed { task, error } => {
+                format!("Task {} failed: {}", task, error.english(dialect))
+            }
+        }
+    }
+}
+
+fn tasks_text(tasks: &[(String, TaskState)], language: Language) -> String {
+    tasks
+        .iter()
+        .map(|(task, state)| format!("{} ({})", task, state.text(language)))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::{ENGLISH, SOMALI};
+
+    #[test]
+    fn test_messages_in_both_languages() {
+        let message = Message::Expected(";", Place::AfterExpression);
+        assert_eq!(
+            message.text(Language::Somali, &SOMALI),
+            "';' ayaa la filayay ka dib tibaaxda"
+        );
+        assert_eq!(
+            message.text(Language::English, &SOMALI),
+            "Expected ';' after expression"
+        );
+
+        let failed = Message::TaskFailed {
+            task: "a".to_string(),
+            error: Box::new(Message::DivisionByZero),
+        };
+        assert_eq!(failed.code(), ErrorCode::DivisionByZero);
+        assert_eq!(
+            failed.text(Language::Somali, &SOMALI),
+            "Hawsha a way fashilantay: Eber wax laguma qaybin karo"
+        );
+    }
+
+    #[test]
+    fn test_language_names() {
+        assert_eq!(Language::from_name("EN"), Some(Language::English));
+        assert_eq!(Language::from_name("so"), Some(Language::Somali));
+        assert_eq!(Language::from_name("fr"), None);
+        assert_eq!(Language::default(), Language::Somali);
+    }
+
+    #[test]
+    fn test_keywords_follow_the_dialect() {
+        let message = Message::ExpectedKeyword(TokenType::Var);
+        assert_eq!(
+            message.text(Language::English, &ENGLISH),
+            "Expected 'var' keyword"
+        );
+        assert_eq!(
+            Language::English.help(ErrorCode::Deadlock, &ENGLISH),
+            Some("every 'receive' needs a task that will 'send' on the same channel".to_string())
+        );
+    }
+}
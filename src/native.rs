@@ -0,0 +1,161 @@
+use crate::{
+    channel::Channel,
+    environment::Environment,
+    error::{Error, Result},
+    expr::ExpLiteralValue,
+    iterator::{iterate, ValueIterator},
+    message::Message,
+    set::Set,
+};
+
+#[derive(Debug, Clone)]
+pub struct NativeFunction {
+    name: &'static str,
+    arity: usize,
+    function: fn(Vec<ExpLiteralValue>) -> Result<ExpLiteralValue>,
+}
+
+impl PartialEq for NativeFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl PartialOrd for NativeFunction {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        self.name.partial_cmp(other.name)
+    }
+}
+
+impl NativeFunction {
+    pub const fn new(
+        name: &'static str,
+        arity: usize,
+        function: fn(Vec<ExpLiteralValue>) -> Result<ExpLiteralValue>,
+    ) -> Self {
+        Self {
+            name,
+            arity,
+            function,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name
+    }
+
+    pub fn call(&self, args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+        if args.len() != self.arity {
+            return Err(Error::runtime(Message::NativeArity {
+                name: self.name.to_string(),
+                expected: self.arity,
+                got: args.len(),
+            }));
+        }
+
+        (self.function)(args)
+    }
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct BoundMethod {
+    receiver: Box<ExpLiteralValue>,
+    method: NativeFunction,
+}
+
+impl BoundMethod {
+    pub fn new(receiver: ExpLiteralValue, method: NativeFunction) -> Self {
+        Self {
+            receiver: Box::new(receiver),
+            method,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        self.method.name()
+    }
+
+    pub fn call(&self, args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+        if args.len() + 1 != self.method.arity {
+            return Err(Error::runtime(Message::NativeArity {
+                name: self.name().to_string(),
+                expected: self.method.arity - 1,
+                got: args.len(),
+            }));
+        }
+
+        let mut receiver_and_args = vec![(*self.receiver).clone()];
+        receiver_and_args.extend(args);
+
+        self.method.call(receiver_and_args)
+    }
+}
+
+const GLOBALS: &[NativeFunction] = &[
+    NativeFunction::new("dherer", 1, length),
+    NativeFunction::new("kanaal", 1, channel),
+    NativeFunction::new("liis", 1, list),
+    NativeFunction::new("urur", 1, set),
+    NativeFunction::new("wadar", 1, sum),
+];
+
+pub fn define_globals(env: &mut Environment) {
+    for native in GLOBALS {
+        env.define(native.name, ExpLiteralValue::NativeValue(native.clone()));
+    }
+}
+
+fn length(args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+    let length = match &args[0] {
+        ExpLiteralValue::ListValue(items) | ExpLiteralValue::TupleValue(items) => items.len(),
+        ExpLiteralValue::SetValue(set) => set.len(),
+        ExpLiteralValue::StringValue(s) => s.chars().count(),
+        ExpLiteralValue::RangeValue(range) => range.len(),
+        value => count(iterate(value)?)?,
+    };
+
+    Ok(ExpLiteralValue::Number(length as f32))
+}
+
+fn channel(args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+    match &args[0] {
+        ExpLiteralValue::Number(n) if *n >= 0.0 && n.fract() == 0.0 => {
+            Ok(ExpLiteralValue::ChannelValue(Channel::new(*n as usize)))
+        }
+        value => Err(Error::runtime(Message::ChannelCapacity(value.to_string()))),
+    }
+}
+
+fn count(iterator: ValueIterator) -> Result<usize> {
+    let mut count = 0;
+    for item in iterator {
+        item?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn list(args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+    let items = iterate(&args[0])?.collect::<Result<Vec<ExpLiteralValue>>>()?;
+
+    Ok(ExpLiteralValue::ListValue(items))
+}
+
+fn set(args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+    let items = iterate(&args[0])?.collect::<Result<Vec<ExpLiteralValue>>>()?;
+
+    Ok(ExpLiteralValue::SetValue(Set::from_values(items)?))
+}
+
+fn sum(args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+    let mut total = 0.0;
+    for item in iterate(&args[0])? {
+        match item? {
+            ExpLiteralValue::Number(n) => total += n,
+            value => return Err(Error::runtime(Message::CannotSum(value.to_string()))),
+        }
+    }
+
+    Ok(ExpLiteralValue::Number(total))
+}
@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const OSMANYA_ZERO: u32 = 0x104A0;
+
+static OSMANYA: AtomicBool = AtomicBool::new(false);
+
+/// The digits numbers are printed with.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Numerals {
+    #[default]
+    Latin,
+    Osmanya,
+}
+
+impl Numerals {
+    pub fn from_name(name: &str) -> Option<Numerals> {
+        match name.to_lowercase().as_str() {
+            "latin" | "ascii" => Some(Numerals::Latin),
+            "osmanya" | "os" | "cismaaniya" => Some(Numerals::Osmanya),
+            _ => None,
+        }
+    }
+
+    // Printing happens deep inside the interpreter, so the choice is a
+    // process-wide setting rather than something threaded through it.
+    pub fn set(self) {
+        OSMANYA.store(self == Numerals::Osmanya, Ordering::Relaxed);
+    }
+
+    pub fn current() -> Numerals {
+        if OSMANYA.load(Ordering::Relaxed) {
+            Numerals::Osmanya
+        } else {
+            Numerals::Latin
+        }
+    }
+
+    pub fn render(self, text: &str) -> String {
+        match self {
+            Numerals::Latin => text.to_string(),
+            Numerals::Osmanya => text
+                .chars()
+                .map(|c| match c {
+                    '0'..='9' => char::from_u32(OSMANYA_ZERO + c as u32 - '0' as u32).unwrap(),
+                    _ => c,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The value of an ASCII or Osmanya decimal digit.
+pub fn digit_value(c: char) -> Option<u32> {
+    match c as u32 {
+        0x30..=0x39 => Some(c as u32 - 0x30),
+        code @ OSMANYA_ZERO..=0x104A9 => Some(code - OSMANYA_ZERO),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osmanya_digits() {
+        assert_eq!(Numerals::Osmanya.render("-120.5"), "-𐒡𐒢𐒠.𐒥");
+        assert_eq!(Numerals::Latin.render("42"), "42");
+        assert_eq!(digit_value('𐒩'), Some(9));
+        assert_eq!(digit_value('7'), Some(7));
+        assert_eq!(digit_value('𐒖'), None);
+    }
+}
@@ -1,117 +1,446 @@
-use anyhow::Result;
+use std::ops::Range;
 
 use crate::{
+    error::{Error, Errors, Result, Span},
     expr::{ExpLiteralValue, Expr},
     lexer::{Token, TokenType},
-    statement::Stmt,
+    message::{Message, Name, Place},
+    pattern::{Pattern, PatternItem},
+    statement::{Case, EnumVariant, MatchArm, Stmt},
+    syntax::NodeKind,
+    trace::{self, Phase},
 };
 
-#[derive(Debug, PartialEq, PartialOrd)]
+// Binding powers from loosest to tightest. An operator is only taken by a
+// `parse_expression` call whose precedence is lower than its own, or equal
+// to it when the operator is right-associative.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
 enum Precedence {
     None,
     Assignment,
+    Ternary,
+    Or,
+    And,
+    Coalesce,
+    Range,
     Equality,
     Comparison,
     Term,
     Factor,
     Unary,
-    Primary,
+    Postfix,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Associativity {
+    Left,
+    Right,
+}
+
+fn infix_operator(token_type: TokenType) -> Option<(Precedence, Associativity)> {
+    use Associativity::*;
+
+    let operator = match token_type {
+        TokenType::Equal => (Precedence::Assignment, Right),
+        TokenType::Question => (Precedence::Ternary, Right),
+        TokenType::Or => (Precedence::Or, Left),
+        TokenType::And => (Precedence::And, Left),
+        TokenType::QuestionQuestion => (Precedence::Coalesce, Left),
+        TokenType::DotDot | TokenType::DotDotEqual => (Precedence::Range, Left),
+        TokenType::EqualEqual | TokenType::BangEqual => (Precedence::Equality, Left),
+        TokenType::Less | TokenType::LessEqual | TokenType::Greater | TokenType::GreaterEqual => {
+            (Precedence::Comparison, Left)
+        }
+        TokenType::Plus | TokenType::Minus => (Precedence::Term, Left),
+        TokenType::Star | TokenType::Slash => (Precedence::Factor, Left),
+        TokenType::LeftParen | TokenType::LeftBracket | TokenType::Dot | TokenType::QuestionDot => {
+            (Precedence::Postfix, Left)
+        }
+        _ => return None,
+    };
+    Some(operator)
 }
 
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
+    errors: Vec<Error>,
+    nodes: Vec<(NodeKind, Range<usize>)>,
 }
 
 impl Parser {
     pub fn new(tokens: Vec<Token>) -> Self {
-        Self { tokens, current: 0 }
+        Self {
+            tokens,
+            current: 0,
+            errors: Vec::new(),
+            nodes: Vec::new(),
+        }
+    }
+
+    /// The token ranges of every statement, expression and pattern parsed
+    /// so far, and of the tokens skipped after each error, innermost first.
+    pub fn take_nodes(&mut self) -> Vec<(NodeKind, Range<usize>)> {
+        std::mem::take(&mut self.nodes)
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>> {
+    /// Parses the whole program, recovering after each syntax error so that
+    /// every one of them is reported together.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Errors> {
         let mut statements = Vec::new();
 
         while !self.is_at_end() {
-            statements.push(self.parse_statement()?);
+            statements.extend(self.parse_declaration());
         }
 
-        Ok(statements)
+        match self.errors.is_empty() {
+            true => Ok(statements),
+            false => Err(Errors(std::mem::take(&mut self.errors))),
+        }
     }
 
-    fn parse_statement(&mut self) -> Result<Stmt> {
-        let peeked = self.peek().token_type;
-        println!("Peeked: {:?}", peeked);
+    fn parse_declaration(&mut self) -> Option<Stmt> {
+        let start = self.current;
+
+        match self.parse_statement() {
+            Ok(statement) => Some(statement),
+            Err(error) => {
+                // Look at the offending token again if it was consumed: it
+                // may be the brace that decides where recovery stops.
+                if self.current > start && error.span == Some(Span::of(&self.previous())) {
+                    self.current -= 1;
+                }
+
+                trace::event(Phase::Parse, || format!("error: {}", error));
+                self.errors.push(error);
+                self.synchronize(start);
+                self.mark(NodeKind::Error, start);
+                None
+            }
+        }
+    }
 
-        let peeked_next = self.peek_next().token_type;
-        if peeked_next == TokenType::LeftParen {
+    // Skips to the next statement boundary: just after a ';' or a block, or
+    // just before a keyword that starts a statement or the '}' closing the
+    // enclosing block. Braces opened by the failed statement are skipped
+    // through to their match, so the statements inside and the closing '}'
+    // are not mistaken for ones of the enclosing block.
+    fn synchronize(&mut self, start: usize) {
+        if self.current == start {
             self.advance();
-            return self.parse_expression_statement();
         }
 
-        match peeked {
+        let mut depth = 0;
+        for token in &self.tokens[start..self.current] {
+            match token.token_type {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth > 0 => depth -= 1,
+                _ => (),
+            }
+        }
+
+        while !self.is_at_end() {
+            if depth == 0 {
+                if self.current > start && self.previous().token_type == TokenType::Semicolon {
+                    return;
+                }
+
+                if matches!(
+                    self.peek().token_type,
+                    TokenType::Var
+                        | TokenType::Const
+                        | TokenType::Fun
+                        | TokenType::Enum
+                        | TokenType::If
+                        | TokenType::While
+                        | TokenType::For
+                        | TokenType::Match
+                        | TokenType::Return
+                        | TokenType::Print
+                        | TokenType::Yield
+                        | TokenType::Spawn
+                        | TokenType::Send
+                        | TokenType::Receive
+                        | TokenType::RightBrace
+                ) {
+                    return;
+                }
+            }
+
+            match self.advance().token_type {
+                TokenType::LeftBrace => depth += 1,
+                TokenType::RightBrace if depth == 1 => return,
+                TokenType::RightBrace => depth -= 1,
+                _ => (),
+            }
+        }
+    }
+
+    fn parse_statement(&mut self) -> Result<Stmt> {
+        let peeked = self.peek().token_type;
+        let _section = trace::section(Phase::Parse, || {
+            let token = self.peek();
+            format!(
+                "statement {:?} at {}:{}",
+                peeked,
+                token.line_number + 1,
+                token.column + 1
+            )
+        });
+
+        let start = self.current;
+        let statement = match peeked {
             TokenType::Var => self.parse_variable_declaration(),
+            TokenType::Const => self.parse_constant_declaration(),
             TokenType::Print => self.parse_print_statement(),
             TokenType::Fun => self.parse_function_declaration(),
+            TokenType::Enum => self.parse_enum_declaration(),
+            TokenType::For => self.parse_for_statement(),
+            TokenType::Match => self.parse_match_statement(),
+            TokenType::Return => self.parse_return_statement(),
+            TokenType::Yield => self.parse_yield_statement(),
+            TokenType::Spawn => self.parse_spawn_statement(),
+            TokenType::Send => self.parse_send_statement(),
+            TokenType::Receive => self.parse_receive_statement(),
             TokenType::LeftBrace => self.parse_block_statement(),
             _ => self.parse_expression_statement(),
-        }
+        }?;
+
+        self.mark(NodeKind::of_stmt(&statement), start);
+        Ok(statement)
     }
 
     fn parse_expression_statement(&mut self) -> Result<Stmt> {
         let expression = self.parse_expression(Precedence::None)?;
-        println!("Expression: {:?}", expression);
+        trace::event(Phase::Parse, || format!("expression {}", expression));
 
-        match self.consume(TokenType::Semicolon, "Expected ';' after expression") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
-        }
+        self.consume(
+            TokenType::Semicolon,
+            Message::Expected(";", Place::AfterExpression),
+        )?;
 
         Ok(Stmt::Expression { expression })
     }
 
     fn parse_block_statement(&mut self) -> Result<Stmt> {
-        println!("Parsing block statement");
-        match self.consume(TokenType::LeftBrace, "Expected '{' to start block") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
-        }
+        let statements = self.parse_block()?;
+
+        Ok(Stmt::Block { statements })
+    }
+
+    fn parse_block(&mut self) -> Result<Vec<Stmt>> {
+        self.consume(
+            TokenType::LeftBrace,
+            Message::Expected("{", Place::BlockStart),
+        )?;
 
         let mut statements = Vec::new();
         while !self.check(TokenType::RightBrace) && !self.is_at_end() {
-            statements.push(self.parse_statement()?);
+            statements.extend(self.parse_declaration());
         }
 
-        match self.consume(TokenType::RightBrace, "Expected '}' to end block") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
+        self.consume(
+            TokenType::RightBrace,
+            Message::Expected("}", Place::BlockEnd),
+        )?;
+
+        Ok(statements)
+    }
+
+    fn parse_for_statement(&mut self) -> Result<Stmt> {
+        self.consume(TokenType::For, Message::ExpectedKeyword(TokenType::For))?;
+
+        let name = self.consume(
+            TokenType::Identifier,
+            Message::ExpectedName(Name::LoopVariable),
+        )?;
+
+        self.consume(TokenType::In, Message::ExpectedKeyword(TokenType::In))?;
+
+        let iterable = self.parse_expression(Precedence::None)?;
+
+        let body = self.parse_block()?;
+
+        Ok(Stmt::ForEach {
+            name,
+            iterable,
+            body,
+        })
+    }
+
+    fn parse_match_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.consume(TokenType::Match, Message::ExpectedKeyword(TokenType::Match))?;
+
+        let subject = self.parse_expression(Precedence::None)?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            Message::Expected("{", Place::BeforeMatchArms),
+        )?;
+
+        let mut arms = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let case = self.parse_case()?;
+            let body = self.parse_block()?;
+            arms.push(MatchArm { case, body });
         }
 
-        Ok(Stmt::Block { statements })
+        self.consume(
+            TokenType::RightBrace,
+            Message::Expected("}", Place::AfterMatchArms),
+        )?;
+
+        Ok(Stmt::Match {
+            keyword,
+            subject,
+            arms,
+        })
     }
 
-    fn parse_function_declaration(&mut self) -> Result<Stmt> {
-        match self.consume(TokenType::Fun, "Expected 'hawl' keyword") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
+    fn parse_case(&mut self) -> Result<Case> {
+        if self.check(TokenType::Else) {
+            return Ok(Case::Else(self.advance()));
+        }
+
+        let enum_name = self.consume(TokenType::Identifier, Message::ExpectedName(Name::Enum))?;
+
+        self.consume(TokenType::Dot, Message::Expected(".", Place::AfterEnumName))?;
+
+        let name = self.consume(TokenType::Identifier, Message::ExpectedName(Name::Variant))?;
+
+        let mut bindings = Vec::new();
+        if self.match_token(TokenType::LeftParen) {
+            loop {
+                bindings.push(
+                    self.consume(TokenType::Identifier, Message::ExpectedName(Name::Variable))?,
+                );
+
+                if !self.match_token(TokenType::Comma) {
+                    break;
+                }
+            }
+
+            self.consume(
+                TokenType::RightParen,
+                Message::Expected(")", Place::AfterVariantFields),
+            )?;
         }
 
-        let name = match self.consume(TokenType::Identifier, "Expected function name") {
-            Ok(token) => token,
-            Err(e) => anyhow::bail!(e),
+        Ok(Case::Variant {
+            enum_name,
+            name,
+            bindings,
+        })
+    }
+
+    fn parse_return_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.consume(
+            TokenType::Return,
+            Message::ExpectedKeyword(TokenType::Return),
+        )?;
+
+        let value = if self.check(TokenType::Semicolon) {
+            None
+        } else {
+            Some(self.parse_expression(Precedence::None)?)
         };
 
-        match self.consume(TokenType::LeftParen, "Expected '(' after function name") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
+        self.consume(
+            TokenType::Semicolon,
+            Message::Expected(";", Place::AfterReturnValue),
+        )?;
+
+        Ok(Stmt::Return { keyword, value })
+    }
+
+    fn parse_yield_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.consume(TokenType::Yield, Message::ExpectedKeyword(TokenType::Yield))?;
+
+        let value = self.parse_expression(Precedence::None)?;
+
+        self.consume(
+            TokenType::Semicolon,
+            Message::Expected(";", Place::AfterYieldedValue),
+        )?;
+
+        Ok(Stmt::Yield { keyword, value })
+    }
+
+    fn parse_spawn_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.consume(TokenType::Spawn, Message::ExpectedKeyword(TokenType::Spawn))?;
+
+        let call = self.parse_expression(Precedence::None)?;
+        if !matches!(call, Expr::Call { .. }) {
+            return Err(Error::parse(Message::SpawnNeedsCall).at(&keyword));
         }
 
+        self.consume(
+            TokenType::Semicolon,
+            Message::Expected(";", Place::AfterSpawnedCall),
+        )?;
+
+        Ok(Stmt::Spawn { keyword, call })
+    }
+
+    fn parse_send_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.consume(TokenType::Send, Message::ExpectedKeyword(TokenType::Send))?;
+
+        let value = self.parse_expression(Precedence::None)?;
+
+        self.consume(TokenType::In, Message::ExpectedKeyword(TokenType::In))?;
+
+        let channel = self.parse_expression(Precedence::None)?;
+
+        self.consume(
+            TokenType::Semicolon,
+            Message::Expected(";", Place::AfterChannel),
+        )?;
+
+        Ok(Stmt::Send {
+            keyword,
+            value,
+            channel,
+        })
+    }
+
+    fn parse_receive_statement(&mut self) -> Result<Stmt> {
+        let keyword = self.consume(
+            TokenType::Receive,
+            Message::ExpectedKeyword(TokenType::Receive),
+        )?;
+
+        let name = self.consume(TokenType::Identifier, Message::ExpectedName(Name::Variable))?;
+
+        self.consume(TokenType::From, Message::ExpectedKeyword(TokenType::From))?;
+
+        let channel = self.parse_expression(Precedence::None)?;
+
+        self.consume(
+            TokenType::Semicolon,
+            Message::Expected(";", Place::AfterChannel),
+        )?;
+
+        Ok(Stmt::Receive {
+            keyword,
+            name,
+            channel,
+        })
+    }
+
+    fn parse_function_declaration(&mut self) -> Result<Stmt> {
+        let keyword = self.consume(TokenType::Fun, Message::ExpectedKeyword(TokenType::Fun))?;
+
+        let name = self.consume(TokenType::Identifier, Message::ExpectedName(Name::Function))?;
+
+        self.consume(
+            TokenType::LeftParen,
+            Message::Expected("(", Place::AfterFunctionName),
+        )?;
+
         let mut params = Vec::new();
         if !self.check(TokenType::RightParen) {
             loop {
-                match self.consume(TokenType::Identifier, "Expected parameter name") {
-                    Ok(token) => params.push(token),
-                    Err(e) => anyhow::bail!(e),
-                }
+                params.push(self.parse_pattern_item()?);
 
                 if !self.match_token(TokenType::Comma) {
                     break;
@@ -119,95 +448,269 @@ impl Parser {
             }
         }
 
-        match self.consume(TokenType::RightParen, "Expected ')' after parameters") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
-        }
+        self.consume(
+            TokenType::RightParen,
+            Message::Expected(")", Place::AfterParameters),
+        )?;
 
-        match self.consume(TokenType::LeftBrace, "Expected '{' before function body") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
-        }
+        let return_type = self.parse_annotation()?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            Message::Expected("{", Place::BeforeFunctionBody),
+        )?;
 
         let mut body = Vec::new();
         while !self.check(TokenType::RightBrace) && !self.is_at_end() {
-            body.push(self.parse_statement()?);
+            body.extend(self.parse_declaration());
         }
 
-        match self.consume(TokenType::RightBrace, "Expected '}' after function body") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
+        self.consume(
+            TokenType::RightBrace,
+            Message::Expected("}", Place::AfterFunctionBody),
+        )?;
+
+        Ok(Stmt::Fun {
+            name,
+            params,
+            return_type,
+            body,
+            doc: keyword.doc,
+        })
+    }
+
+    fn parse_enum_declaration(&mut self) -> Result<Stmt> {
+        self.consume(TokenType::Enum, Message::ExpectedKeyword(TokenType::Enum))?;
+
+        let name = self.consume(TokenType::Identifier, Message::ExpectedName(Name::Enum))?;
+
+        self.consume(
+            TokenType::LeftBrace,
+            Message::Expected("{", Place::BeforeEnumVariants),
+        )?;
+
+        let mut variants = Vec::new();
+        while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+            let variant_name =
+                self.consume(TokenType::Identifier, Message::ExpectedName(Name::Variant))?;
+
+            let mut fields = Vec::new();
+            if self.match_token(TokenType::LeftParen) {
+                loop {
+                    fields.push(
+                        self.consume(TokenType::Identifier, Message::ExpectedName(Name::Field))?,
+                    );
+
+                    if !self.match_token(TokenType::Comma) {
+                        break;
+                    }
+                }
+
+                self.consume(
+                    TokenType::RightParen,
+                    Message::Expected(")", Place::AfterVariantFields),
+                )?;
+            }
+
+            variants.push(EnumVariant::new(variant_name, fields));
+
+            if !self.match_token(TokenType::Comma) {
+                break;
+            }
         }
 
-        Ok(Stmt::Fun { name, params, body })
+        self.consume(
+            TokenType::RightBrace,
+            Message::Expected("}", Place::AfterEnumVariants),
+        )?;
+
+        Ok(Stmt::Enum { name, variants })
     }
 
     fn parse_variable_declaration(&mut self) -> Result<Stmt> {
-        match self.consume(TokenType::Var, "Expected 'weel' keyword") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
-        }
-        let name = match self.consume(TokenType::Identifier, "Expected variable name") {
-            Ok(token) => token,
-            Err(e) => anyhow::bail!(e),
-        };
+        let keyword = self.consume(TokenType::Var, Message::ExpectedKeyword(TokenType::Var))?;
+        let pattern = self.parse_pattern()?;
+        let annotation = self.parse_annotation()?;
 
         let initializer = if self.match_token(TokenType::Equal) {
             Some(self.parse_expression(Precedence::None)?)
+        } else if !matches!(pattern, Pattern::Identifier(_)) {
+            return Err(Error::parse(Message::PatternNeedsInitializer).at(self.peek()));
         } else {
             None
         };
 
-        match self.consume(
+        self.consume(
             TokenType::Semicolon,
-            "Expected ';' after variable declaration",
-        ) {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
-        }
+            Message::Expected(";", Place::AfterVariableDeclaration),
+        )?;
 
         Ok(Stmt::Var {
-            name,
-            initializer: match initializer {
-                Some(expr) => Some(expr),
-                None => None,
-            },
+            pattern,
+            annotation,
+            initializer,
+            doc: keyword.doc,
         })
     }
 
-    fn parse_print_statement(&mut self) -> Result<Stmt> {
-        match self.consume(TokenType::Print, "Expected 'print' keyword") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
+    fn parse_pattern(&mut self) -> Result<Pattern> {
+        let start = self.current;
+        let pattern = self.parse_pattern_shape()?;
+        self.mark(NodeKind::Pattern, start);
+        Ok(pattern)
+    }
+
+    fn parse_pattern_shape(&mut self) -> Result<Pattern> {
+        if self.check(TokenType::LeftBracket) {
+            let bracket = self.advance();
+            let mut elements = Vec::new();
+            let mut rest = None;
+
+            while !self.check(TokenType::RightBracket) && !self.is_at_end() {
+                if self.match_token(TokenType::DotDotDot) {
+                    rest = Some(
+                        self.consume(TokenType::Identifier, Message::ExpectedName(Name::Rest))?,
+                    );
+                    break;
+                }
+
+                elements.push(self.parse_pattern_item()?);
+
+                if !self.match_token(TokenType::Comma) {
+                    break;
+                }
+            }
+
+            self.consume(
+                TokenType::RightBracket,
+                Message::Expected("]", Place::AfterListPattern),
+            )?;
+
+            return Ok(Pattern::List {
+                bracket,
+                elements,
+                rest,
+            });
         }
-        let expression = self.parse_expression(Precedence::None)?;
 
-        match self.consume(TokenType::Semicolon, "Expected ';' after print statement") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
+        if self.check(TokenType::LeftBrace) {
+            let brace = self.advance();
+            let mut fields = Vec::new();
+
+            while !self.check(TokenType::RightBrace) && !self.is_at_end() {
+                let name =
+                    self.consume(TokenType::Identifier, Message::ExpectedName(Name::Field))?;
+                fields.push(PatternItem {
+                    pattern: Pattern::Identifier(name),
+                    annotation: None,
+                    default: self.parse_pattern_default()?,
+                });
+
+                if !self.match_token(TokenType::Comma) {
+                    break;
+                }
+            }
+
+            self.consume(
+                TokenType::RightBrace,
+                Message::Expected("}", Place::AfterObjectPattern),
+            )?;
+
+            return Ok(Pattern::Object { brace, fields });
+        }
+
+        Ok(Pattern::Identifier(self.consume(
+            TokenType::Identifier,
+            Message::ExpectedName(Name::VariableOrPattern),
+        )?))
+    }
+
+    fn parse_pattern_item(&mut self) -> Result<PatternItem> {
+        let pattern = self.parse_pattern()?;
+        let annotation = self.parse_annotation()?;
+        let default = self.parse_pattern_default()?;
+
+        Ok(PatternItem {
+            pattern,
+            annotation,
+            default,
+        })
+    }
+
+    fn parse_annotation(&mut self) -> Result<Option<Token>> {
+        if !self.match_token(TokenType::Colon) {
+            return Ok(None);
+        }
+
+        Ok(Some(self.consume(
+            TokenType::Identifier,
+            Message::ExpectedName(Name::Type),
+        )?))
+    }
+
+    fn parse_pattern_default(&mut self) -> Result<Option<Expr>> {
+        if self.match_token(TokenType::Equal) {
+            Ok(Some(self.parse_expression(Precedence::Assignment)?))
+        } else {
+            Ok(None)
         }
+    }
+
+    fn parse_constant_declaration(&mut self) -> Result<Stmt> {
+        self.consume(TokenType::Const, Message::ExpectedKeyword(TokenType::Const))?;
+        let name = self.consume(TokenType::Identifier, Message::ExpectedName(Name::Constant))?;
+
+        self.consume(
+            TokenType::Equal,
+            Message::Expected("=", Place::AfterConstantName),
+        )?;
+
+        let initializer = self.parse_expression(Precedence::None)?;
+
+        self.consume(
+            TokenType::Semicolon,
+            Message::Expected(";", Place::AfterConstantDeclaration),
+        )?;
+
+        Ok(Stmt::Const { name, initializer })
+    }
+
+    fn parse_print_statement(&mut self) -> Result<Stmt> {
+        self.consume(TokenType::Print, Message::ExpectedKeyword(TokenType::Print))?;
+        let expression = self.parse_expression(Precedence::None)?;
+
+        self.consume(
+            TokenType::Semicolon,
+            Message::Expected(";", Place::AfterPrint),
+        )?;
 
         Ok(Stmt::Print { expression })
     }
 
     fn parse_expression(&mut self, precedence: Precedence) -> Result<Expr> {
+        let start = self.current;
         let mut left = self.parse_prefix()?;
+        self.mark(NodeKind::of_expr(&left), start);
 
-        if precedence < self.get_precedence() {
-            let token = self.advance();
-            println!("Token: {:?}", token.token_type);
-
-            if token.token_type == TokenType::LeftParen {
-                println!("Call expression");
-                return self.finish_call(left);
+        while let Some((operator, associativity)) = infix_operator(self.peek().token_type) {
+            let binds = operator > precedence
+                || (operator == precedence && associativity == Associativity::Right);
+            if !binds {
+                break;
             }
-            left = self.parse_infix(left, token)?;
+
+            let token = self.advance();
+            left = self.parse_infix(left, token, operator)?;
+            self.mark(NodeKind::of_expr(&left), start);
         }
 
         Ok(left)
     }
 
     fn parse_prefix(&mut self) -> Result<Expr> {
+        if self.is_at_end() {
+            return Err(unexpected(self.peek()));
+        }
         let token = self.advance();
 
         match token.token_type {
@@ -226,25 +729,129 @@ impl Parser {
                     right: Box::new(right),
                 })
             }
+            TokenType::LeftBracket => {
+                let mut elements = Vec::new();
+
+                if !self.check(TokenType::RightBracket) {
+                    loop {
+                        elements.push(self.parse_expression(Precedence::None)?);
+
+                        if !self.match_token(TokenType::Comma) {
+                            break;
+                        }
+                    }
+                }
+
+                self.consume(
+                    TokenType::RightBracket,
+                    Message::Expected("]", Place::AfterListElements),
+                )?;
+
+                Ok(Expr::List { elements })
+            }
             TokenType::LeftParen => {
+                if self.match_token(TokenType::RightParen) {
+                    return Ok(Expr::Tuple {
+                        elements: Vec::new(),
+                    });
+                }
+
                 let expr = self.parse_expression(Precedence::None)?;
-                match self.consume(TokenType::RightParen, "Exprected ')' after expression") {
-                    Ok(_) => (),
-                    Err(e) => anyhow::bail!(e),
+                if self.match_token(TokenType::Comma) {
+                    return self.finish_tuple(expr);
                 }
+
+                self.consume(
+                    TokenType::RightParen,
+                    Message::Expected(")", Place::AfterExpression),
+                )?;
                 Ok(Expr::Grouping {
                     expression: Box::new(expr),
                 })
             }
-            _ => anyhow::bail!("Unexpected token: {:?}", token),
+            _ => Err(unexpected(&token)),
         }
     }
 
-    fn parse_infix(&mut self, left: Expr, token: Token) -> Result<Expr> {
-        let precedence = self.get_precedence();
+    // `(a,)` is a one-element tuple; `(a)` is just a grouping.
+    fn finish_tuple(&mut self, first: Expr) -> Result<Expr> {
+        let mut elements = vec![first];
+        while !self.check(TokenType::RightParen) && !self.is_at_end() {
+            elements.push(self.parse_expression(Precedence::None)?);
+
+            if !self.match_token(TokenType::Comma) {
+                break;
+            }
+        }
+
+        self.consume(
+            TokenType::RightParen,
+            Message::Expected(")", Place::AfterTupleElements),
+        )?;
+
+        Ok(Expr::Tuple { elements })
+    }
+
+    fn finish_get(&mut self, object: Expr, optional: bool) -> Result<Expr> {
+        let name = self.consume(TokenType::Identifier, Message::ExpectedName(Name::Property))?;
+
+        Ok(Expr::Get {
+            object: Box::new(object),
+            name,
+            optional,
+        })
+    }
+
+    fn finish_index(&mut self, object: Expr, optional: bool) -> Result<Expr> {
+        let index = self.parse_expression(Precedence::None)?;
+
+        let bracket = self.consume(
+            TokenType::RightBracket,
+            Message::Expected("]", Place::AfterIndex),
+        )?;
+
+        Ok(Expr::Index {
+            object: Box::new(object),
+            bracket,
+            index: Box::new(index),
+            optional,
+        })
+    }
+
+    // The branch after `:` is parsed at ternary precedence, so
+    // `a ? b : c ? d : e` nests to the right.
+    fn finish_ternary(&mut self, condition: Expr) -> Result<Expr> {
+        let then_branch = self.parse_expression(Precedence::None)?;
+
+        self.consume(
+            TokenType::Colon,
+            Message::Expected(":", Place::InConditional),
+        )?;
+
+        let else_branch = self.parse_expression(Precedence::Ternary)?;
+
+        Ok(Expr::Ternary {
+            condition: Box::new(condition),
+            then_branch: Box::new(then_branch),
+            else_branch: Box::new(else_branch),
+        })
+    }
+
+    fn parse_infix(&mut self, left: Expr, token: Token, precedence: Precedence) -> Result<Expr> {
+        match token.token_type {
+            TokenType::LeftParen => return self.finish_call(left),
+            TokenType::LeftBracket => return self.finish_index(left, false),
+            TokenType::Dot => return self.finish_get(left, false),
+            TokenType::QuestionDot if self.match_token(TokenType::LeftBracket) => {
+                return self.finish_index(left, true)
+            }
+            TokenType::QuestionDot => return self.finish_get(left, true),
+            TokenType::Question => return self.finish_ternary(left),
+            _ => (),
+        }
+
         let right = self.parse_expression(precedence)?;
         match token.token_type {
-            TokenType::LeftParen => self.finish_call(left),
             TokenType::Plus
             | TokenType::Minus
             | TokenType::Star
@@ -254,12 +861,26 @@ impl Parser {
             | TokenType::Less
             | TokenType::LessEqual
             | TokenType::Greater
-            | TokenType::GreaterEqual => Ok(Expr::Binary {
+            | TokenType::GreaterEqual
+            | TokenType::DotDot
+            | TokenType::DotDotEqual => Ok(Expr::Binary {
                 left: Box::new(left),
                 operator: token,
                 right: Box::new(right),
             }),
-            _ => anyhow::bail!("Unexpected token: {:?}", token),
+            TokenType::Equal => match left {
+                Expr::Variable { name } => Ok(Expr::Assignment {
+                    name,
+                    value: Box::new(right),
+                }),
+                _ => Err(Error::parse(Message::InvalidAssignmentTarget).at(&token)),
+            },
+            TokenType::QuestionQuestion | TokenType::Or | TokenType::And => Ok(Expr::Logical {
+                left: Box::new(left),
+                operator: token,
+                right: Box::new(right),
+            }),
+            _ => Err(unexpected(&token)),
         }
     }
 
@@ -276,10 +897,10 @@ impl Parser {
             }
         }
 
-        match self.consume(TokenType::RightParen, "Expected ')' after arguments") {
-            Ok(_) => (),
-            Err(e) => anyhow::bail!(e),
-        }
+        self.consume(
+            TokenType::RightParen,
+            Message::Expected(")", Place::AfterArguments),
+        )?;
 
         Ok(Expr::Call {
             callee: Box::new(callee),
@@ -288,27 +909,9 @@ impl Parser {
         })
     }
 
-    fn get_precedence(&self) -> Precedence {
-        let token = self.peek();
-        match token.token_type {
-            TokenType::EqualEqual | TokenType::BangEqual => Precedence::Equality,
-            TokenType::Less
-            | TokenType::LessEqual
-            | TokenType::Greater
-            | TokenType::GreaterEqual => Precedence::Comparison,
-            TokenType::Plus => Precedence::Term,
-            TokenType::Star | TokenType::Slash => Precedence::Factor,
-            TokenType::Equal => Precedence::Assignment,
-            TokenType::Bang => Precedence::Unary,
-            TokenType::Minus => {
-                if self.peek().token_type == TokenType::Number {
-                    Precedence::Term
-                } else {
-                    Precedence::Unary
-                }
-            }
-
-            _ => Precedence::None,
+    fn mark(&mut self, kind: NodeKind, start: usize) {
+        if self.current > start {
+            self.nodes.push((kind, start..self.current));
         }
     }
 
@@ -320,12 +923,21 @@ impl Parser {
         false
     }
 
-    fn consume(&mut self, token_type: TokenType, message: &str) -> Result<Token, String> {
+    fn consume(&mut self, token_type: TokenType, message: Message) -> Result<Token> {
         if self.check(token_type) {
             return Ok(self.advance());
         }
 
-        Err(message.to_string())
+        let error = Error::parse(message);
+        let previous = &self.tokens[self.current.saturating_sub(1)];
+
+        // A token missing at the end of a line belongs after the last one
+        // written, not at the start of whatever follows.
+        if self.current > 0 && self.peek().line_number > previous.line_number {
+            return Err(error.with_span(Span::after(previous)));
+        }
+
+        Err(error.at(self.peek()))
     }
 
     fn check(&self, token_type: TokenType) -> bool {
@@ -343,22 +955,26 @@ impl Parser {
     }
 
     fn is_at_end(&self) -> bool {
-        self.peek().token_type == TokenType::EOF
+        self.peek().token_type == TokenType::Eof
     }
 
     fn peek(&self) -> &Token {
         &self.tokens[self.current]
     }
 
-    fn peek_next(&self) -> &Token {
-        &self.tokens[self.current + 1]
-    }
-
     fn previous(&self) -> Token {
         self.tokens[self.current - 1].clone()
     }
 }
 
+fn unexpected(token: &Token) -> Error {
+    let message = match token.token_type {
+        TokenType::Eof => Message::UnexpectedEof,
+        _ => Message::UnexpectedToken(token.lexeme.clone()),
+    };
+    Error::parse(message).at(token)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -419,4 +1035,162 @@ mod tests {
 
         assert_eq!(expr, "(* (+ (- 1) 2) 3)");
     }
+
+    fn parse_errors(source: &str) -> Vec<(usize, String)> {
+        let tokens = Lexer::new(source).lex().unwrap();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+
+        errors
+            .0
+            .into_iter()
+            .map(|error| (error.span.unwrap().line + 1, error.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parser_reports_every_error() {
+        let errors = parse_errors(
+            "weel x = 1
+             weel y = 2;
+             hawl f() {
+                 weel z = ;
+                 weel w = z;
+             }
+             markasta i ku { weel q = ; }
+             joogto k 3;
+             weel ok = 1;",
+        );
+
+        assert_eq!(
+            errors,
+            vec![
+                (1, "Expected ';' after variable declaration".to_string()),
+                (4, "Unexpected token ';'".to_string()),
+                (7, "Unexpected token '{'".to_string()),
+                (8, "Expected '=' after constant name".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_missing_loop_and_channel_keywords() {
+        assert_eq!(
+            parse_errors(
+                "markasta i 1..3 {}
+                 dir 1 c;
+                 qaado x c;"
+            ),
+            vec![
+                (1, "Expected 'ku' keyword".to_string()),
+                (2, "Expected 'ku' keyword".to_string()),
+                (3, "Expected 'ka' keyword".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expression_at_end_of_input() {
+        assert_eq!(
+            parse_errors("weel x = "),
+            vec![(1, "Unexpected end of file".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_parser_recovers_inside_blocks() {
+        let tokens = Lexer::new("{ weel a = ; weel b = 1; }").lex().unwrap();
+        let mut parser = Parser::new(tokens);
+        let errors = parser.parse().unwrap_err();
+
+        assert_eq!(errors.0.len(), 1);
+        assert_eq!(
+            parse_errors("}\nweel a = 1;"),
+            vec![(1, "Unexpected token '}'".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_doc_comments_on_declarations() {
+        let source = "/// Wuxuu celiyaa laba jeer.\nhawl labo(n) { celi n * 2; }\n/// Tirada.\nweel x = 1;\ndaabac x;";
+        let tokens = Lexer::new(source).lex().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        let docs: Vec<Option<&str>> = statements
+            .iter()
+            .map(|statement| match statement {
+                Stmt::Fun { doc, .. } | Stmt::Var { doc, .. } => doc.as_deref(),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            docs,
+            vec![Some("Wuxuu celiyaa laba jeer."), Some("Tirada."), None]
+        );
+    }
+
+    #[test]
+    fn test_precedence_and_associativity() {
+        let cases = [
+            ("1 - 2 - 3", "(- (- 1 2) 3)"),
+            ("8 / 4 / 2", "(/ (/ 8 4) 2)"),
+            ("2 * 3 + 1", "(+ (* 2 3) 1)"),
+            ("1 + 2 * 3 - 4", "(- (+ 1 (* 2 3)) 4)"),
+            ("1 - -2", "(- 1 (- 2))"),
+            ("-a * b", "(* (- (var a)) (var b))"),
+            ("!a == b", "(== (! (var a)) (var b))"),
+            (
+                "a < b == c >= d",
+                "(== (< (var a) (var b)) (>= (var c) (var d)))",
+            ),
+            ("0..n + 1", "(.. 0 (+ (var n) 1))"),
+            ("a ?? b ?? c", "(?? (?? (var a) (var b)) (var c))"),
+            ("a ?? b ? 1 : 2", "(? (?? (var a) (var b)) 1 2)"),
+            ("a ama b iyo c", "(ama (var a) (iyo (var b) (var c)))"),
+            (
+                "a == 1 iyo b ?? c",
+                "(iyo (== (var a) 1) (?? (var b) (var c)))",
+            ),
+            ("a ama b ? 1 : 2", "(? (ama (var a) (var b)) 1 2)"),
+            ("a ? 1 : b ? 2 : 3", "(? (var a) 1 (? (var b) 2 3))"),
+            ("a = b = 1 + 2", "(= a (= b (+ 1 2)))"),
+            ("a = b ? 1 : 2", "(= a (? (var b) 1 2))"),
+            ("-f(x)", "(- ((var f)((var x))))"),
+            ("f(1)(2)", "(((var f)(1))(2))"),
+            ("a.b[0](c)", "(([] (. (var a) b) 0)((var c)))"),
+            ("a?.b?.[i] * 2", "(* (?.[] (?. (var a) b) (var i)) 2)"),
+            ("(1 + 2) * 3", "(* (+ 1 2) 3)"),
+        ];
+
+        for (source, expected) in cases {
+            let tokens = Lexer::new(source).lex().unwrap();
+            let mut parser = Parser::new(tokens);
+            let expr = parser.parse_expression(Precedence::None).unwrap();
+
+            assert_eq!(expr.to_string(), expected, "{}", source);
+            assert!(parser.is_at_end(), "{} was not fully parsed", source);
+        }
+    }
+
+    #[test]
+    fn test_call_statement() {
+        let tokens = Lexer::new("f(x); g();").lex().unwrap();
+        let statements = Parser::new(tokens).parse().unwrap();
+
+        let expressions: Vec<String> = statements
+            .iter()
+            .map(|statement| match statement {
+                Stmt::Expression { expression } => expression.to_string(),
+                other => panic!("not an expression statement: {:?}", other),
+            })
+            .collect();
+        assert_eq!(expressions, vec!["((var f)((var x)))", "((var g)())"]);
+    }
+
+    #[test]
+    fn test_invalid_assignment_target() {
+        let tokens = Lexer::new("a + b = c;").lex().unwrap();
+        let errors = Parser::new(tokens).parse().unwrap_err();
+
+        assert_eq!(errors.0[0].to_string(), "Invalid assignment target");
+    }
 }
@@ -0,0 +1,136 @@
+use crate::{
+    environment::Environment,
+    error::{Error, Result},
+    expr::{ExpLiteralValue, Expr},
+    iterator::iterate,
+    lexer::Token,
+    message::Message,
+};
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub enum Pattern {
+    Identifier(Token),
+    List {
+        bracket: Token,
+        elements: Vec<PatternItem>,
+        rest: Option<Token>,
+    },
+    Object {
+        brace: Token,
+        fields: Vec<PatternItem>,
+    },
+}
+
+// One element of a list pattern, one field of an object pattern, or one
+// function parameter. Object fields are always `Pattern::Identifier`.
+// The type annotation is only read by the checker.
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct PatternItem {
+    pub pattern: Pattern,
+    pub annotation: Option<Token>,
+    pub default: Option<Expr>,
+}
+
+impl Pattern {
+    pub fn names(&self) -> Vec<&Token> {
+        match self {
+            Pattern::Identifier(name) => vec![name],
+            Pattern::List { elements, rest, .. } => elements
+                .iter()
+                .flat_map(|element| element.pattern.names())
+                .chain(rest.iter())
+                .collect(),
+            Pattern::Object { fields, .. } => fields
+                .iter()
+                .flat_map(|field| field.pattern.names())
+                .collect(),
+        }
+    }
+
+    pub fn bind(&self, value: ExpLiteralValue, env: &mut Environment) -> Result<()> {
+        match self {
+            Pattern::Identifier(name) => env.define(&name.lexeme, value),
+            Pattern::List {
+                bracket,
+                elements,
+                rest,
+            } => {
+                let mut items = match iterate(&value) {
+                    Ok(items) => items,
+                    Err(_) => {
+                        return Err(Error::runtime(Message::CannotDestructureList(
+                            value.to_string(),
+                        ))
+                        .at(bracket))
+                    }
+                };
+
+                for (count, element) in elements.iter().enumerate() {
+                    let item = items.next().transpose()?;
+                    if item.is_none() && element.default.is_none() {
+                        return Err(Error::runtime(Message::ListPatternTooShort {
+                            expected: elements.iter().filter(|e| e.default.is_none()).count(),
+                            got: count,
+                        })
+                        .at(bracket));
+                    }
+                    element.bind(item, env)?;
+                }
+
+                match rest {
+                    Some(rest) => {
+                        let rest_items = items.collect::<Result<Vec<ExpLiteralValue>>>()?;
+                        env.define(&rest.lexeme, ExpLiteralValue::ListValue(rest_items));
+                    }
+                    None => {
+                        if items.next().transpose()?.is_some() {
+                            return Err(Error::runtime(Message::ListPatternTooLong(
+                                elements.len(),
+                            ))
+                            .at(bracket));
+                        }
+                    }
+                }
+            }
+            Pattern::Object { brace, fields } => {
+                let variant = match &value {
+                    ExpLiteralValue::VariantValue(variant) => variant,
+                    _ => {
+                        return Err(Error::runtime(Message::CannotDestructureObject(
+                            value.to_string(),
+                        ))
+                        .at(brace))
+                    }
+                };
+
+                for field in fields {
+                    let name = field.pattern.names()[0];
+                    let item = variant.field(&name.lexeme).cloned();
+                    if item.is_none() && field.default.is_none() {
+                        return Err(Error::runtime(Message::MissingDestructuredField {
+                            value: value.to_string(),
+                            field: name.lexeme.clone(),
+                        })
+                        .at(brace));
+                    }
+                    field.bind(item, env)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl PatternItem {
+    // Binds `value`, falling back to the default when the value is missing.
+    pub fn bind(&self, value: Option<ExpLiteralValue>, env: &mut Environment) -> Result<()> {
+        let value = match (value, &self.default) {
+            (Some(value), _) => value,
+            (None, Some(default)) => default.evaluate(env)?,
+            (None, None) => ExpLiteralValue::Nil,
+        };
+
+        self.pattern.bind(value, env)
+    }
+}
@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use crate::{
+    error::{Error, Result},
+    expr::Expr,
+    lexer::Token,
+    message::Message,
+    pattern::Pattern,
+    statement::Stmt,
+    trace::{self, Phase},
+};
+
+#[derive(Debug, Clone, Copy)]
+enum Binding {
+    Variable,
+    Constant(usize),
+}
+
+// Walks the program before it runs and rejects assignments to constants
+// that can be seen statically. Names it cannot find, such as globals from
+// earlier REPL lines, are left for the interpreter to check.
+pub struct Resolver {
+    scopes: Vec<HashMap<String, Binding>>,
+}
+
+impl Resolver {
+    pub fn new() -> Self {
+        Self {
+            scopes: vec![HashMap::new()],
+        }
+    }
+
+    pub fn resolve(&mut self, statements: &[Stmt]) -> Result<()> {
+        for statement in statements {
+            self.resolve_statement(statement)?;
+        }
+
+        Ok(())
+    }
+
+    fn resolve_statement(&mut self, statement: &Stmt) -> Result<()> {
+        match statement {
+            Stmt::Expression { expression } | Stmt::Print { expression } => {
+                self.resolve_expression(expression)?;
+            }
+            Stmt::Var {
+                pattern,
+                initializer,
+                ..
+            } => {
+                if let Some(initializer) = initializer {
+                    self.resolve_expression(initializer)?;
+                }
+                self.resolve_pattern(pattern)?;
+            }
+            Stmt::Const { name, initializer } => {
+                self.resolve_expression(initializer)?;
+                self.declare(name, Binding::Constant(name.line_number));
+            }
+            Stmt::Fun {
+                name, params, body, ..
+            } => {
+                self.declare(name, Binding::Variable);
+                self.scoped(|resolver| {
+                    for param in params {
+                        if let Some(default) = &param.default {
+                            resolver.resolve_expression(default)?;
+                        }
+                        resolver.resolve_pattern(&param.pattern)?;
+                    }
+                    resolver.resolve(body)
+                })?;
+            }
+            Stmt::Enum { name, .. } => self.declare(name, Binding::Variable),
+            Stmt::Block { statements } => {
+                self.scoped(|resolver| resolver.resolve(statements))?;
+            }
+            Stmt::ForEach {
+                name,
+                iterable,
+                body,
+            } => {
+                self.resolve_expression(iterable)?;
+                self.scoped(|resolver| {
+                    resolver.declare(name, Binding::Variable);
+                    resolver.resolve(body)
+                })?;
+            }
+            Stmt::Match { subject, arms, .. } => {
+                self.resolve_expression(subject)?;
+                for arm in arms {
+                    self.scoped(|resolver| {
+                        for binding in arm.case.bindings() {
+                            resolver.declare(binding, Binding::Variable);
+                        }
+                        resolver.resolve(&arm.body)
+                    })?;
+                }
+            }
+            Stmt::Yield { value, .. } => self.resolve_expression(value)?,
+            Stmt::Return { value, .. } => {
+                if let Some(value) = value {
+                    self.resolve_expression(value)?;
+                }
+            }
+            Stmt::Spawn { call, .. } => self.resolve_expression(call)?,
+            Stmt::Send { value, channel, .. } => {
+                self.resolve_expression(value)?;
+                self.resolve_expression(channel)?;
+            }
+            Stmt::Receive { name, channel, .. } => {
+                self.resolve_expression(channel)?;
+                self.declare(name, Binding::Variable);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_expression(&mut self, expression: &Expr) -> Result<()> {
+        match expression {
+            Expr::Assignment { name, value } => {
+                self.resolve_expression(value)?;
+                if let Some(Binding::Constant(line)) = self.lookup(name) {
+                    return Err(Error::resolve(Message::AssignToConstant {
+                        name: name.lexeme.clone(),
+                        line: line + 1,
+                    })
+                    .at(name));
+                }
+            }
+            Expr::Literal { .. } | Expr::Variable { .. } => (),
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expression(left)?;
+                self.resolve_expression(right)?;
+            }
+            Expr::Unary { right, .. } => self.resolve_expression(right)?,
+            Expr::Grouping { expression } => self.resolve_expression(expression)?,
+            Expr::Call {
+                callee, arguments, ..
+            } => {
+                self.resolve_expression(callee)?;
+                for argument in arguments {
+                    self.resolve_expression(argument)?;
+                }
+            }
+            Expr::Get { object, .. } => self.resolve_expression(object)?,
+            Expr::Index { object, index, .. } => {
+                self.resolve_expression(object)?;
+                self.resolve_expression(index)?;
+            }
+            Expr::Ternary {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                self.resolve_expression(condition)?;
+                self.resolve_expression(then_branch)?;
+                self.resolve_expression(else_branch)?;
+            }
+            Expr::List { elements } | Expr::Tuple { elements } => {
+                for element in elements {
+                    self.resolve_expression(element)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn resolve_pattern(&mut self, pattern: &Pattern) -> Result<()> {
+        match pattern {
+            Pattern::Identifier(name) => self.declare(name, Binding::Variable),
+            Pattern::List { elements, rest, .. } => {
+                for element in elements {
+                    if let Some(default) = &element.default {
+                        self.resolve_expression(default)?;
+                    }
+                    self.resolve_pattern(&element.pattern)?;
+                }
+                if let Some(rest) = rest {
+                    self.declare(rest, Binding::Variable);
+                }
+            }
+            Pattern::Object { fields, .. } => {
+                for field in fields {
+                    if let Some(default) = &field.default {
+                        self.resolve_expression(default)?;
+                    }
+                    self.resolve_pattern(&field.pattern)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn declare(&mut self, name: &Token, binding: Binding) {
+        trace::event(Phase::Resolve, || {
+            format!("declare {} as {:?}", name.lexeme, binding)
+        });
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.lexeme.clone(), binding);
+        }
+    }
+
+    fn lookup(&self, name: &Token) -> Option<Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(&name.lexeme).copied())
+    }
+
+    fn scoped(&mut self, resolve: impl FnOnce(&mut Self) -> Result<()>) -> Result<()> {
+        self.scopes.push(HashMap::new());
+        let _section = trace::section(Phase::Resolve, || "scope".to_string());
+        let result = resolve(self);
+        self.scopes.pop();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::Parser};
+
+    fn resolve(source: &str) -> Result<()> {
+        let tokens = Lexer::new(source).lex()?;
+        let statements = Parser::new(tokens).parse()?;
+        Resolver::new().resolve(&statements)
+    }
+
+    #[test]
+    fn test_assign_to_constant() {
+        let error = resolve("weel x = 1;\njoogto PI = 3.14;\n{ x = 2; PI = 3; }").unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Cannot assign to constant 'PI' declared on line 2"
+        );
+    }
+
+    #[test]
+    fn test_shadowed_constant_is_assignable() {
+        resolve("joogto n = 1; hawl f(n) { n = 2; } markasta n ku 0..3 { n = 5; }").unwrap();
+    }
+}
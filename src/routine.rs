@@ -0,0 +1,366 @@
+use crate::{
+    channel::{Channel, Offer},
+    environment::Environment,
+    error::{Error, Result, ResultExt},
+    expr::{ExpLiteralValue, Expr},
+    iterator::{iterate, ValueIterator},
+    lexer::Token,
+    message::{Message, TaskState},
+    statement::Stmt,
+    trace::{self, Phase},
+};
+
+pub enum Completion {
+    Yielded(ExpLiteralValue),
+    Returned(ExpLiteralValue),
+    Finished,
+    Blocked,
+    Spawned { name: String, routine: Routine },
+}
+
+enum Wait {
+    Send {
+        channel: Channel,
+        value: ExpLiteralValue,
+    },
+    Delivery {
+        channel: Channel,
+        ticket: u64,
+    },
+    Receive {
+        channel: Channel,
+        name: String,
+    },
+}
+
+enum Frame {
+    Block {
+        statements: Vec<Stmt>,
+        index: usize,
+        scoped: bool,
+    },
+    Loop {
+        name: Token,
+        iterator: ValueIterator,
+        body: Vec<Stmt>,
+    },
+}
+
+/// A list of statements executed on an explicit frame stack instead of the
+/// Rust call stack, so that execution can stop at a `bixi` and pick up from
+/// the same place on the next `resume`.
+pub struct Routine {
+    env: Environment,
+    frames: Vec<Frame>,
+    waiting: Option<Wait>,
+    steps: usize,
+}
+
+impl std::fmt::Debug for Routine {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "Routine {{ frames: {} }}", self.frames.len())
+    }
+}
+
+impl Routine {
+    pub fn new(statements: Vec<Stmt>, env: Environment) -> Self {
+        Self {
+            env,
+            frames: vec![Frame::Block {
+                statements,
+                index: 0,
+                scoped: false,
+            }],
+            waiting: None,
+            steps: 0,
+        }
+    }
+
+    // Counts executed statements and completed channel operations, so the
+    // scheduler can tell whether a task got anywhere since its last turn.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    pub fn state(&self) -> TaskState {
+        match self.waiting {
+            Some(Wait::Send { .. }) | Some(Wait::Delivery { .. }) => TaskState::Sending,
+            Some(Wait::Receive { .. }) => TaskState::Receiving,
+            None => TaskState::Runnable,
+        }
+    }
+
+    pub fn into_environment(mut self) -> Environment {
+        self.unwind();
+        self.env
+    }
+
+    pub fn resume(&mut self) -> Result<Completion> {
+        let result = self.run();
+        if result.is_err() {
+            self.unwind();
+        }
+        result
+    }
+
+    fn run(&mut self) -> Result<Completion> {
+        if let Some(wait) = self.waiting.take() {
+            if let Some(wait) = self.poll(wait) {
+                self.waiting = Some(wait);
+                return Ok(Completion::Blocked);
+            }
+        }
+
+        loop {
+            let frame = match self.frames.last_mut() {
+                Some(frame) => frame,
+                None => return Ok(Completion::Finished),
+            };
+
+            match frame {
+                Frame::Block {
+                    statements,
+                    index,
+                    scoped,
+                } => {
+                    if *index >= statements.len() {
+                        let scoped = *scoped;
+                        self.frames.pop();
+                        if scoped {
+                            self.pop_scope();
+                        }
+                        continue;
+                    }
+
+                    let statement = statements[*index].clone();
+                    *index += 1;
+
+                    if let Some(completion) = self.step(statement)? {
+                        return Ok(completion);
+                    }
+                }
+                Frame::Loop {
+                    name,
+                    iterator,
+                    body,
+                } => match iterator.next() {
+                    Some(item) => {
+                        let item = item?;
+                        let name = name.lexeme.clone();
+                        let body = body.clone();
+
+                        self.push_scope();
+                        self.env.define(&name, item);
+                        self.frames.push(Frame::Block {
+                            statements: body,
+                            index: 0,
+                            scoped: true,
+                        });
+                    }
+                    None => {
+                        self.frames.pop();
+                    }
+                },
+            }
+        }
+    }
+
+    fn step(&mut self, statement: Stmt) -> Result<Option<Completion>> {
+        self.steps += 1;
+        // The frame stack stands in for Rust's call stack here, so it sets
+        // how deeply the statement is nested.
+        trace::event_at(Phase::Run, self.frames.len() - 1, || statement.describe());
+
+        match statement {
+            Stmt::Block { statements } => {
+                self.push_scope();
+                self.frames.push(Frame::Block {
+                    statements,
+                    index: 0,
+                    scoped: true,
+                });
+            }
+            Stmt::ForEach {
+                name,
+                iterable,
+                body,
+            } => {
+                let iterable = iterable.evaluate(&mut self.env)?;
+                let iterator = iterate(&iterable).or_at(&name)?;
+                self.frames.push(Frame::Loop {
+                    name,
+                    iterator,
+                    body,
+                });
+            }
+            Stmt::Match {
+                keyword,
+                subject,
+                arms,
+            } => {
+                let value = subject.evaluate(&mut self.env)?;
+                for arm in arms {
+                    if let Some(fields) = arm.case.matches(&value, &mut self.env)? {
+                        self.push_scope();
+                        for (name, value) in fields {
+                            self.env.define(&name, value);
+                        }
+                        self.frames.push(Frame::Block {
+                            statements: arm.body,
+                            index: 0,
+                            scoped: true,
+                        });
+                        return Ok(None);
+                    }
+                }
+
+                return Err(Error::runtime(Message::NoMatchingArm(value.to_string())).at(&keyword));
+            }
+            Stmt::Yield { keyword: _, value } => {
+                let value = value.evaluate(&mut self.env)?;
+                return Ok(Some(Completion::Yielded(value)));
+            }
+            Stmt::Return { keyword: _, value } => {
+                let value = match value {
+                    Some(expr) => expr.evaluate(&mut self.env)?,
+                    None => ExpLiteralValue::Nil,
+                };
+                self.unwind();
+                return Ok(Some(Completion::Returned(value)));
+            }
+            Stmt::Spawn { keyword: _, call } => {
+                let (callee, arguments) = match call {
+                    Expr::Call {
+                        callee, arguments, ..
+                    } => (callee, arguments),
+                    _ => unreachable!("the parser only accepts calls after 'bilow'"),
+                };
+
+                let function = match callee.evaluate(&mut self.env)? {
+                    ExpLiteralValue::FunctionValue(function) => function,
+                    value => return Err(Error::runtime(Message::CannotSpawn(value.to_string()))),
+                };
+                let arguments = arguments
+                    .iter()
+                    .map(|arg| arg.evaluate(&mut self.env))
+                    .collect::<Result<Vec<ExpLiteralValue>>>()?;
+
+                let routine = function.start(arguments)?;
+                return Ok(Some(Completion::Spawned {
+                    name: function.name().to_string(),
+                    routine,
+                }));
+            }
+            Stmt::Send {
+                keyword: _,
+                value,
+                channel,
+            } => {
+                let value = value.evaluate(&mut self.env)?;
+                let channel = evaluate_channel(&channel, &mut self.env)?;
+                return Ok(self.block_on(Wait::Send { channel, value }));
+            }
+            Stmt::Receive {
+                keyword: _,
+                name,
+                channel,
+            } => {
+                let channel = evaluate_channel(&channel, &mut self.env)?;
+                return Ok(self.block_on(Wait::Receive {
+                    channel,
+                    name: name.lexeme,
+                }));
+            }
+            statement => statement.execute(&mut self.env)?,
+        }
+
+        Ok(None)
+    }
+
+    fn block_on(&mut self, wait: Wait) -> Option<Completion> {
+        self.waiting = self.poll(wait);
+        self.waiting.as_ref().map(|_| Completion::Blocked)
+    }
+
+    // Tries to finish a channel operation, returning it again if it still
+    // has to wait.
+    fn poll(&mut self, wait: Wait) -> Option<Wait> {
+        match wait {
+            Wait::Send { channel, value } => match channel.offer(value) {
+                Offer::Accepted => {
+                    self.steps += 1;
+                    None
+                }
+                Offer::Queued(ticket) => {
+                    self.steps += 1;
+                    self.poll(Wait::Delivery { channel, ticket })
+                }
+                Offer::Full(value) => Some(Wait::Send {
+                    channel,
+                    value: *value,
+                }),
+            },
+            Wait::Delivery { channel, ticket } => {
+                if channel.is_taken(ticket) {
+                    self.steps += 1;
+                    None
+                } else {
+                    Some(Wait::Delivery { channel, ticket })
+                }
+            }
+            Wait::Receive { channel, name } => match channel.take() {
+                Some(value) => {
+                    self.steps += 1;
+                    self.env.define(&name, value);
+                    None
+                }
+                None => Some(Wait::Receive { channel, name }),
+            },
+        }
+    }
+
+    fn push_scope(&mut self) {
+        let outer = std::mem::take(&mut self.env);
+        self.env = Environment::enclosing(outer);
+    }
+
+    fn pop_scope(&mut self) {
+        let scope = std::mem::take(&mut self.env);
+        self.env = scope
+            .into_enclosing()
+            .expect("scoped environment always has an enclosing environment");
+    }
+
+    fn unwind(&mut self) {
+        self.waiting = None;
+        while let Some(frame) = self.frames.pop() {
+            match frame {
+                Frame::Block { scoped: true, .. } => self.pop_scope(),
+                Frame::Block { .. } | Frame::Loop { .. } => (),
+            }
+        }
+    }
+}
+
+/// Runs control-flow statements that appear outside of a function body.
+pub fn execute(statement: &Stmt, env: &mut Environment) -> Result<()> {
+    let mut routine = Routine::new(vec![statement.clone()], std::mem::take(env));
+    let completion = routine.resume();
+    *env = routine.into_environment();
+
+    match completion? {
+        Completion::Finished => Ok(()),
+        Completion::Returned(_) => Err(Error::runtime(Message::ReturnOutsideFunction)),
+        Completion::Yielded(_) => Err(Error::runtime(Message::YieldOutsideFunction)),
+        Completion::Blocked | Completion::Spawned { .. } => {
+            Err(Error::runtime(Message::NestedTask))
+        }
+    }
+}
+
+fn evaluate_channel(channel: &Expr, env: &mut Environment) -> Result<Channel> {
+    match channel.evaluate(env)? {
+        ExpLiteralValue::ChannelValue(channel) => Ok(channel),
+        value => Err(Error::runtime(Message::NotAChannel(value.to_string()))),
+    }
+}
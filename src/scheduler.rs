@@ -0,0 +1,120 @@
+use crate::{
+    error::{Error, Result},
+    message::Message,
+    routine::{Completion, Routine},
+    trace::{self, Phase},
+};
+
+struct Task {
+    name: String,
+    routine: Routine,
+    done: bool,
+    top_level: bool,
+}
+
+// Runs tasks in the order they were spawned, each until it blocks or
+// finishes, so a program always produces the same interleaving.
+#[derive(Default)]
+pub struct Scheduler {
+    tasks: Vec<Task>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self { tasks: Vec::new() }
+    }
+
+    pub fn spawn_main(&mut self, routine: Routine) {
+        self.push("main", routine, true);
+    }
+
+    pub fn spawn(&mut self, name: &str, routine: Routine) {
+        self.push(name, routine, false);
+    }
+
+    fn push(&mut self, name: &str, routine: Routine, top_level: bool) {
+        let name = format!("{}#{}", name, self.tasks.len());
+        self.tasks.push(Task {
+            name,
+            routine,
+            done: false,
+            top_level,
+        });
+    }
+
+    pub fn run(&mut self) -> Result<()> {
+        loop {
+            let mut progressed = false;
+
+            let mut id = 0;
+            while id < self.tasks.len() {
+                if !self.tasks[id].done {
+                    progressed |= self.run_task(id)?;
+                }
+                id += 1;
+            }
+
+            let blocked = self
+                .tasks
+                .iter()
+                .filter(|task| !task.done)
+                .map(|task| (task.name.clone(), task.routine.state()))
+                .collect::<Vec<_>>();
+
+            if blocked.is_empty() {
+                return Ok(());
+            }
+
+            if !progressed {
+                return Err(Error::runtime(Message::Deadlock(blocked)));
+            }
+        }
+    }
+
+    // Removes every task and hands back the routine of the main task.
+    pub fn finish(&mut self) -> Option<Routine> {
+        let tasks = std::mem::take(&mut self.tasks);
+        tasks
+            .into_iter()
+            .find(|task| task.top_level)
+            .map(|task| task.routine)
+    }
+
+    fn run_task(&mut self, id: usize) -> Result<bool> {
+        let steps = self.tasks[id].routine.steps();
+        let _section = trace::section(Phase::Run, || format!("task {}", self.tasks[id].name));
+
+        loop {
+            let task = &mut self.tasks[id];
+            let completion = match task.routine.resume() {
+                Ok(completion) => completion,
+                Err(e) if task.top_level => return Err(e),
+                Err(mut error) => {
+                    error.message = Box::new(Message::TaskFailed {
+                        task: task.name.clone(),
+                        error: error.message,
+                    });
+                    return Err(error);
+                }
+            };
+
+            match completion {
+                Completion::Spawned { name, routine } => self.spawn(&name, routine),
+                Completion::Blocked => break,
+                Completion::Returned(_) if task.top_level => {
+                    return Err(Error::runtime(Message::ReturnOutsideFunction))
+                }
+                Completion::Returned(_) | Completion::Finished => {
+                    task.done = true;
+                    break;
+                }
+                Completion::Yielded(_) => {
+                    return Err(Error::runtime(Message::YieldOutsideFunction))
+                }
+            }
+        }
+
+        let task = &self.tasks[id];
+        Ok(task.done || task.routine.steps() != steps)
+    }
+}
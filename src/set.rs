@@ -0,0 +1,139 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeSet,
+    hash::{Hash, Hasher},
+};
+
+use crate::{
+    error::{Error, Result},
+    expr::ExpLiteralValue,
+    lexer::Token,
+    message::Message,
+    native::{BoundMethod, NativeFunction},
+};
+
+const METHODS: &[NativeFunction] = &[NativeFunction::new("leeyahay", 2, contains)];
+
+// Orders set members with `ExpLiteralValue::total_cmp`, which unlike
+// `PartialOrd` is defined for every pair of hashable values.
+#[derive(Debug, Clone)]
+struct Member(ExpLiteralValue);
+
+impl PartialEq for Member {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Member {}
+
+impl PartialOrd for Member {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Member {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// An immutable set of hashable values, kept in `total_cmp` order so that
+/// iteration and printing are deterministic.
+#[derive(Debug, Clone, PartialEq, PartialOrd, Default)]
+pub struct Set {
+    members: BTreeSet<Member>,
+}
+
+// Only hashable values become members, and none of those contain the
+// interior mutability that clippy sees through `ExpLiteralValue`.
+#[allow(clippy::mutable_key_type)]
+impl Set {
+    pub fn from_values(values: impl IntoIterator<Item = ExpLiteralValue>) -> Result<Self> {
+        let mut members = BTreeSet::new();
+        for value in values {
+            if !value.is_hashable() {
+                return Err(Error::runtime(Message::NotHashable(value.to_string())));
+            }
+            members.insert(Member(value));
+        }
+
+        Ok(Self { members })
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn contains(&self, value: &ExpLiteralValue) -> bool {
+        self.members.contains(&Member(value.clone()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ExpLiteralValue> {
+        self.members.iter().map(|member| &member.0)
+    }
+
+    pub fn union(&self, other: &Set) -> Set {
+        Set {
+            members: self.members.union(&other.members).cloned().collect(),
+        }
+    }
+
+    pub fn intersection(&self, other: &Set) -> Set {
+        Set {
+            members: self.members.intersection(&other.members).cloned().collect(),
+        }
+    }
+
+    pub fn difference(&self, other: &Set) -> Set {
+        Set {
+            members: self.members.difference(&other.members).cloned().collect(),
+        }
+    }
+
+    pub fn is_subset(&self, other: &Set) -> bool {
+        self.members.is_subset(&other.members)
+    }
+
+    pub fn get(&self, name: &Token) -> Result<ExpLiteralValue> {
+        match METHODS.iter().find(|method| method.name() == name.lexeme) {
+            Some(method) => Ok(ExpLiteralValue::MethodValue(BoundMethod::new(
+                ExpLiteralValue::SetValue(self.clone()),
+                method.clone(),
+            ))),
+            None => Err(Error::runtime(Message::SetProperty(name.lexeme.clone()))),
+        }
+    }
+}
+
+impl Hash for Set {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.len().hash(state);
+        for value in self.iter() {
+            value.hash(state);
+        }
+    }
+}
+
+impl std::fmt::Display for Set {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let members = self
+            .iter()
+            .map(|value| value.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        write!(f, "{{{}}}", members)
+    }
+}
+
+fn contains(args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+    match &args[0] {
+        ExpLiteralValue::SetValue(set) => Ok(ExpLiteralValue::from_bool(set.contains(&args[1]))),
+        value => Err(Error::runtime(Message::ExpectedSet(value.to_string()))),
+    }
+}
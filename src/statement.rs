@@ -1,9 +1,13 @@
-use anyhow::Result;
-
 use crate::{
     environment::Environment,
+    error::{Error, Result, ResultExt},
     expr::{ExpLiteralValue, Expr},
+    generator::Generator,
     lexer::Token,
+    message::Message,
+    pattern::{Pattern, PatternItem},
+    routine::{self, Completion, Routine},
+    trace::{self, Phase},
 };
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
@@ -15,84 +19,495 @@ pub enum Stmt {
         expression: Expr,
     },
     Var {
-        name: Token,
+        pattern: Pattern,
+        annotation: Option<Token>,
         initializer: Option<Expr>,
+        doc: Option<String>,
+    },
+    Const {
+        name: Token,
+        initializer: Expr,
     },
     Fun {
         name: Token,
-        params: Vec<Token>,
+        params: Vec<PatternItem>,
+        return_type: Option<Token>,
         body: Vec<Stmt>,
+        doc: Option<String>,
+    },
+    Enum {
+        name: Token,
+        variants: Vec<EnumVariant>,
     },
     Block {
         statements: Vec<Stmt>,
     },
+    ForEach {
+        name: Token,
+        iterable: Expr,
+        body: Vec<Stmt>,
+    },
+    Match {
+        keyword: Token,
+        subject: Expr,
+        arms: Vec<MatchArm>,
+    },
+    Yield {
+        keyword: Token,
+        value: Expr,
+    },
+    Return {
+        keyword: Token,
+        value: Option<Expr>,
+    },
+    Spawn {
+        keyword: Token,
+        call: Expr,
+    },
+    Send {
+        keyword: Token,
+        value: Expr,
+        channel: Expr,
+    },
+    Receive {
+        keyword: Token,
+        name: Token,
+        channel: Expr,
+    },
 }
 
 impl Stmt {
-    pub fn execute(&self, env: &mut Environment) -> Result<()> {
+    /// A one-line summary for traces.
+    pub fn describe(&self) -> String {
         match self {
-            Stmt::Fun { name, params, body } => {
+            Stmt::Expression { expression } => format!("expression {}", expression),
+            Stmt::Print { expression } => format!("print {}", expression),
+            Stmt::Var {
+                pattern: Pattern::Identifier(name),
+                ..
+            } => format!("var {}", name.lexeme),
+            Stmt::Var { .. } => "var (pattern)".to_string(),
+            Stmt::Const { name, .. } => format!("const {}", name.lexeme),
+            Stmt::Fun { name, .. } => format!("fun {}", name.lexeme),
+            Stmt::Enum { name, .. } => format!("enum {}", name.lexeme),
+            Stmt::Block { .. } => "block".to_string(),
+            Stmt::ForEach { name, iterable, .. } => {
+                format!("for {} in {}", name.lexeme, iterable)
+            }
+            Stmt::Match { subject, .. } => format!("match {}", subject),
+            Stmt::Yield { value, .. } => format!("yield {}", value),
+            Stmt::Return { value: None, .. } => "return".to_string(),
+            Stmt::Return {
+                value: Some(value), ..
+            } => format!("return {}", value),
+            Stmt::Spawn { call, .. } => format!("spawn {}", call),
+            Stmt::Send { value, channel, .. } => format!("send {} to {}", value, channel),
+            Stmt::Receive { name, channel, .. } => {
+                format!("receive {} from {}", name.lexeme, channel)
+            }
+        }
+    }
+
+    pub fn execute(&self, env: &mut Environment) -> Result<(), Error> {
+        match self {
+            Stmt::Fun {
+                name, params, body, ..
+            } => {
                 let function =
                     Function::new(name.clone(), params.clone(), body.clone(), env.clone());
 
                 env.define(&name.lexeme, ExpLiteralValue::FunctionValue(function));
             }
+            Stmt::Enum { name, variants } => {
+                let enumeration = Enum::new(name.clone(), variants.clone());
+
+                env.define(&name.lexeme, ExpLiteralValue::EnumValue(enumeration));
+            }
             Stmt::Expression { expression } => {
                 expression.evaluate(env)?;
             }
             Stmt::Print { expression } => {
                 let value = expression.evaluate(env)?;
-                println!("{}", value.to_string());
+                println!("{}", value);
             }
-            Stmt::Var { name, initializer } => {
+            Stmt::Var {
+                pattern,
+                initializer,
+                ..
+            } => {
                 let value = match initializer {
                     Some(expr) => expr.evaluate(env)?,
                     None => ExpLiteralValue::Nil,
                 };
-                env.define(&name.lexeme, value);
+                pattern.bind(value, env)?;
             }
-            Stmt::Block { statements } => {
-                let mut block_env = Environment::enclosing(env.clone());
-
-                for statement in statements {
-                    statement.execute(&mut block_env)?;
-                }
+            Stmt::Const { name, initializer } => {
+                let value = initializer.evaluate(env)?;
+                env.define_constant(&name.lexeme, value, name.line_number);
             }
+            Stmt::Block { .. }
+            | Stmt::ForEach { .. }
+            | Stmt::Match { .. }
+            | Stmt::Yield { .. }
+            | Stmt::Return { .. }
+            | Stmt::Spawn { .. }
+            | Stmt::Send { .. }
+            | Stmt::Receive { .. } => routine::execute(self, env)?,
         }
 
         Ok(())
     }
+
+    pub fn contains_yield(&self) -> bool {
+        match self {
+            Stmt::Yield { .. } => true,
+            Stmt::Block { statements } => statements.iter().any(Stmt::contains_yield),
+            Stmt::ForEach { body, .. } => body.iter().any(Stmt::contains_yield),
+            Stmt::Match { arms, .. } => arms
+                .iter()
+                .any(|arm| arm.body.iter().any(Stmt::contains_yield)),
+            _ => false,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, PartialOrd, Clone)]
 pub struct Function {
     name: Token,
-    params: Vec<Token>,
+    params: Vec<PatternItem>,
     body: Vec<Stmt>,
     enclosing: Environment,
+    is_generator: bool,
 }
 
 impl Function {
-    pub fn new(name: Token, params: Vec<Token>, body: Vec<Stmt>, enclosing: Environment) -> Self {
+    pub fn new(
+        name: Token,
+        params: Vec<PatternItem>,
+        body: Vec<Stmt>,
+        enclosing: Environment,
+    ) -> Self {
+        let is_generator = body.iter().any(Stmt::contains_yield);
+
         Self {
             name,
             params,
             body,
             enclosing,
+            is_generator,
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name.lexeme
+    }
+
     pub fn call(&self, args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+        let _section = trace::section(Phase::Run, || format!("call {}", self.name()));
+        let mut routine = Routine::new(self.body.clone(), self.bind(args)?);
+
+        if self.is_generator {
+            return Ok(ExpLiteralValue::GeneratorValue(Generator::new(
+                self.name(),
+                routine,
+            )));
+        }
+
+        match routine.resume()? {
+            Completion::Returned(value) => Ok(value),
+            Completion::Finished => Ok(ExpLiteralValue::Nil),
+            Completion::Yielded(_) => unreachable!("only generator functions can yield"),
+            Completion::Blocked | Completion::Spawned { .. } => {
+                Err(Error::runtime(Message::NestedTask))
+            }
+        }
+    }
+
+    pub fn start(&self, args: Vec<ExpLiteralValue>) -> Result<Routine> {
+        if self.is_generator {
+            return Err(Error::runtime(Message::CannotSpawnGenerator(
+                self.name().to_string(),
+            )));
+        }
+
+        Ok(Routine::new(self.body.clone(), self.bind(args)?))
+    }
+
+    fn bind(&self, args: Vec<ExpLiteralValue>) -> Result<Environment> {
+        let required = self
+            .params
+            .iter()
+            .take_while(|param| param.default.is_none())
+            .count();
+        if args.len() < required {
+            return Err(Error::runtime(Message::FunctionArity {
+                name: self.name().to_string(),
+                required,
+                got: args.len(),
+            }));
+        }
+
         let mut env = Environment::enclosing(self.enclosing.clone());
+        let mut args = args.into_iter();
 
-        for (param, arg) in self.params.iter().zip(args.iter()) {
-            env.define(&param.lexeme, arg.clone());
+        for param in &self.params {
+            param.bind(args.next(), &mut env)?;
         }
 
-        for statement in &self.body {
-            statement.execute(&mut env)?;
+        Ok(env)
+    }
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct EnumVariant {
+    name: Token,
+    fields: Vec<Token>,
+}
+
+impl EnumVariant {
+    pub fn new(name: Token, fields: Vec<Token>) -> Self {
+        Self { name, fields }
+    }
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct Enum {
+    name: Token,
+    variants: Vec<EnumVariant>,
+}
+
+impl Enum {
+    pub fn new(name: Token, variants: Vec<EnumVariant>) -> Self {
+        Self { name, variants }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name.lexeme
+    }
+
+    fn find(&self, name: &Token) -> Result<&EnumVariant> {
+        match self
+            .variants
+            .iter()
+            .find(|variant| variant.name.lexeme == name.lexeme)
+        {
+            Some(variant) => Ok(variant),
+            None => {
+                let valid = self
+                    .variants
+                    .iter()
+                    .map(|variant| variant.name.lexeme.clone())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Err(Error::runtime(Message::UndefinedVariant {
+                    name: name.lexeme.clone(),
+                    enum_name: self.name().to_string(),
+                    valid,
+                }))
+            }
+        }
+    }
+
+    pub fn variant(&self, name: &Token) -> Result<ExpLiteralValue> {
+        let variant = self.find(name)?;
+
+        let constructor = Constructor {
+            enum_name: self.name().to_string(),
+            variant: variant.clone(),
+        };
+
+        if variant.fields.is_empty() {
+            constructor.construct(Vec::new())
+        } else {
+            Ok(ExpLiteralValue::ConstructorValue(constructor))
+        }
+    }
+}
+
+/// What a `dooro` arm matches: one variant, naming its fields in order,
+/// or `kale` for any value the arms before it did not match.
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub enum Case {
+    Variant {
+        enum_name: Token,
+        name: Token,
+        bindings: Vec<Token>,
+    },
+    Else(Token),
+}
+
+impl Case {
+    pub fn bindings(&self) -> &[Token] {
+        match self {
+            Case::Variant { bindings, .. } => bindings,
+            Case::Else(_) => &[],
+        }
+    }
+
+    /// The values to bind to the arm's names if `value` matches it. The arm
+    /// is checked against its enum first, so a misspelt variant is an error
+    /// rather than an arm that never runs.
+    pub fn matches(
+        &self,
+        value: &ExpLiteralValue,
+        env: &mut Environment,
+    ) -> Result<Option<Vec<(String, ExpLiteralValue)>>> {
+        let (enum_name, name, bindings) = match self {
+            Case::Variant {
+                enum_name,
+                name,
+                bindings,
+            } => (enum_name, name, bindings),
+            Case::Else(_) => return Ok(Some(Vec::new())),
+        };
+
+        let enumeration = match (Expr::Variable {
+            name: enum_name.clone(),
+        })
+        .evaluate(env)?
+        {
+            ExpLiteralValue::EnumValue(enumeration) => enumeration,
+            value => {
+                return Err(Error::runtime(Message::NotAnEnum(value.to_string())).at(enum_name))
+            }
+        };
+        let variant = enumeration.find(name).or_at(name)?;
+        if variant.fields.len() != bindings.len() {
+            return Err(Error::runtime(Message::VariantArity {
+                name: format!("{}.{}", enumeration.name(), name.lexeme),
+                expected: variant.fields.len(),
+                got: bindings.len(),
+            })
+            .at(name));
+        }
+
+        match value {
+            ExpLiteralValue::VariantValue(value)
+                if value.enum_name == enumeration.name() && value.name == name.lexeme =>
+            {
+                let names = bindings.iter().map(|binding| binding.lexeme.clone());
+                let values = value.fields.iter().map(|(_, value)| value.clone());
+                Ok(Some(names.zip(values).collect()))
+            }
+            _ => Ok(None),
         }
+    }
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct MatchArm {
+    pub case: Case,
+    pub body: Vec<Stmt>,
+}
 
-        Ok(ExpLiteralValue::Nil)
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct Constructor {
+    enum_name: String,
+    variant: EnumVariant,
+}
+
+impl Constructor {
+    pub fn name(&self) -> String {
+        format!("{}.{}", self.enum_name, self.variant.name.lexeme)
+    }
+
+    pub fn construct(&self, args: Vec<ExpLiteralValue>) -> Result<ExpLiteralValue> {
+        if args.len() != self.variant.fields.len() {
+            return Err(Error::runtime(Message::VariantArity {
+                name: self.name().to_string(),
+                expected: self.variant.fields.len(),
+                got: args.len(),
+            }));
+        }
+
+        let fields = self
+            .variant
+            .fields
+            .iter()
+            .map(|field| field.lexeme.clone())
+            .zip(args)
+            .collect();
+
+        Ok(ExpLiteralValue::VariantValue(Variant {
+            enum_name: self.enum_name.clone(),
+            name: self.variant.name.lexeme.clone(),
+            fields,
+        }))
+    }
+}
+
+#[derive(Debug, PartialEq, PartialOrd, Clone)]
+pub struct Variant {
+    enum_name: String,
+    name: String,
+    fields: Vec<(String, ExpLiteralValue)>,
+}
+
+impl Variant {
+    pub fn is_hashable(&self) -> bool {
+        self.fields.iter().all(|(_, value)| value.is_hashable())
+    }
+
+    pub fn total_cmp(&self, other: &Variant) -> std::cmp::Ordering {
+        self.enum_name
+            .cmp(&other.enum_name)
+            .then_with(|| self.name.cmp(&other.name))
+            .then_with(|| {
+                let values = |variant: &Variant| {
+                    ExpLiteralValue::TupleValue(
+                        variant
+                            .fields
+                            .iter()
+                            .map(|(_, value)| value.clone())
+                            .collect(),
+                    )
+                };
+                values(self).total_cmp(&values(other))
+            })
+    }
+
+    pub fn field(&self, name: &str) -> Option<&ExpLiteralValue> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+    }
+
+    pub fn get(&self, name: &Token) -> Result<ExpLiteralValue> {
+        match self.fields.iter().find(|(field, _)| *field == name.lexeme) {
+            Some((_, value)) => Ok(value.clone()),
+            None => Err(Error::runtime(Message::VariantField {
+                variant: format!("{}.{}", self.enum_name, self.name),
+                field: name.lexeme.clone(),
+            })),
+        }
+    }
+}
+
+impl std::hash::Hash for Variant {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.enum_name.hash(state);
+        self.name.hash(state);
+        for (_, value) in &self.fields {
+            value.hash(state);
+        }
+    }
+}
+
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}.{}", self.enum_name, self.name)?;
+
+        if !self.fields.is_empty() {
+            let values = self
+                .fields
+                .iter()
+                .map(|(_, value)| value.to_string())
+                .collect::<Vec<String>>()
+                .join(", ");
+            write!(f, "({})", values)?;
+        }
+
+        Ok(())
     }
 }
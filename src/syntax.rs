@@ -0,0 +1,545 @@
+use std::{cmp::Reverse, ops::Range};
+
+use crate::{
+    dialect::Dialect,
+    error::{Error, Errors},
+    expr::Expr,
+    lexer::{Lexer, Token},
+    parser::Parser,
+    statement::Stmt,
+};
+
+/// What a node in the syntax tree holds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Program,
+    // Input the parser or lexer could not make sense of.
+    Error,
+
+    ExpressionStmt,
+    PrintStmt,
+    VarDecl,
+    ConstDecl,
+    FunDecl,
+    EnumDecl,
+    Block,
+    ForStmt,
+    MatchStmt,
+    YieldStmt,
+    ReturnStmt,
+    SpawnStmt,
+    SendStmt,
+    ReceiveStmt,
+
+    Pattern,
+
+    Literal,
+    Name,
+    Unary,
+    Binary,
+    Logical,
+    Assignment,
+    Ternary,
+    Call,
+    Field,
+    Index,
+    Group,
+    List,
+    Tuple,
+}
+
+impl NodeKind {
+    pub fn of_stmt(statement: &Stmt) -> NodeKind {
+        match statement {
+            Stmt::Expression { .. } => NodeKind::ExpressionStmt,
+            Stmt::Print { .. } => NodeKind::PrintStmt,
+            Stmt::Var { .. } => NodeKind::VarDecl,
+            Stmt::Const { .. } => NodeKind::ConstDecl,
+            Stmt::Fun { .. } => NodeKind::FunDecl,
+            Stmt::Enum { .. } => NodeKind::EnumDecl,
+            Stmt::Block { .. } => NodeKind::Block,
+            Stmt::ForEach { .. } => NodeKind::ForStmt,
+            Stmt::Match { .. } => NodeKind::MatchStmt,
+            Stmt::Yield { .. } => NodeKind::YieldStmt,
+            Stmt::Return { .. } => NodeKind::ReturnStmt,
+            Stmt::Spawn { .. } => NodeKind::SpawnStmt,
+            Stmt::Send { .. } => NodeKind::SendStmt,
+            Stmt::Receive { .. } => NodeKind::ReceiveStmt,
+        }
+    }
+
+    pub fn of_expr(expression: &Expr) -> NodeKind {
+        match expression {
+            Expr::Literal { .. } => NodeKind::Literal,
+            Expr::Variable { .. } => NodeKind::Name,
+            Expr::Unary { .. } => NodeKind::Unary,
+            Expr::Binary { .. } => NodeKind::Binary,
+            Expr::Logical { .. } => NodeKind::Logical,
+            Expr::Assignment { .. } => NodeKind::Assignment,
+            Expr::Ternary { .. } => NodeKind::Ternary,
+            Expr::Call { .. } => NodeKind::Call,
+            Expr::Get { .. } => NodeKind::Field,
+            Expr::Index { .. } => NodeKind::Index,
+            Expr::Grouping { .. } => NodeKind::Group,
+            Expr::List { .. } => NodeKind::List,
+            Expr::Tuple { .. } => NodeKind::Tuple,
+        }
+    }
+}
+
+/// Source text between tokens that the parser does not see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriviaKind {
+    Whitespace,
+    Newline,
+    LineComment,
+    DocComment,
+    BlockComment,
+    // Text the lexer rejected; it always sits inside an `Error` node.
+    Skipped,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub text: String,
+    pub offset: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(Token),
+    Trivia(Trivia),
+}
+
+impl SyntaxElement {
+    pub fn range(&self) -> Range<usize> {
+        match self {
+            SyntaxElement::Node(node) => node.range.clone(),
+            SyntaxElement::Token(token) => token.offset..token.offset + token.lexeme.len(),
+            SyntaxElement::Trivia(trivia) => trivia.offset..trivia.offset + trivia.text.len(),
+        }
+    }
+}
+
+/// A node of the lossless syntax tree. Its children, in order, hold every
+/// byte of the source it covers, including whitespace and comments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub range: Range<usize>,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    fn new(kind: NodeKind, children: Vec<SyntaxElement>) -> Self {
+        let start = children.first().map_or(0, |child| child.range().start);
+        let end = children.last().map_or(start, |child| child.range().end);
+        Self {
+            kind,
+            range: start..end,
+            children,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+        self.write_text(&mut text);
+        text
+    }
+
+    fn write_text(&self, text: &mut String) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.write_text(text),
+                SyntaxElement::Token(token) => text.push_str(&token.lexeme),
+                SyntaxElement::Trivia(trivia) => text.push_str(&trivia.text),
+            }
+        }
+    }
+
+    /// The tokens under this node, without trivia.
+    pub fn tokens(&self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        self.collect_tokens(&mut tokens);
+        tokens
+    }
+
+    fn collect_tokens(&self, tokens: &mut Vec<Token>) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.collect_tokens(tokens),
+                SyntaxElement::Token(token) => tokens.push(token.clone()),
+                SyntaxElement::Trivia(_) => (),
+            }
+        }
+    }
+
+    fn write_tree(&self, f: &mut std::fmt::Formatter, depth: usize) -> std::fmt::Result {
+        let indent = "  ".repeat(depth);
+        writeln!(f, "{}{:?}@{:?}", indent, self.kind, self.range)?;
+
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.write_tree(f, depth + 1)?,
+                SyntaxElement::Token(token) => writeln!(
+                    f,
+                    "{}  {:?}@{:?} {:?}",
+                    indent,
+                    token.token_type,
+                    child.range(),
+                    token.lexeme
+                )?,
+                SyntaxElement::Trivia(trivia) => writeln!(
+                    f,
+                    "{}  {:?}@{:?} {:?}",
+                    indent,
+                    trivia.kind,
+                    child.range(),
+                    trivia.text
+                )?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        self.write_tree(f, 0)
+    }
+}
+
+/// A program as written, for tools such as formatters that must give back
+/// exactly the text they were handed. Lexing and parsing carry on past
+/// errors, so a tree is built for any input.
+pub struct SyntaxTree {
+    pub root: SyntaxNode,
+    pub errors: Vec<Error>,
+}
+
+impl SyntaxTree {
+    pub fn parse(source: &str, dialect: &'static Dialect) -> SyntaxTree {
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+        for item in Lexer::with_dialect(source, dialect) {
+            match item {
+                Ok(token) => tokens.push(token),
+                Err(error) => errors.push(error),
+            }
+        }
+
+        // A token the lexer rejected leaves a hole for the parser to trip
+        // over. Parse errors between the tokens either side of the hole only
+        // repeat the lex error, so they are dropped.
+        let holes: Vec<Range<usize>> = errors
+            .iter()
+            .filter_map(|error| error.span)
+            .map(|span| {
+                let end = |token: &Token| token.offset + token.lexeme.len();
+                let start = tokens
+                    .iter()
+                    .rev()
+                    .find(|token| end(token) <= span.offset)
+                    .map_or(0, end);
+                let stop = tokens
+                    .iter()
+                    .find(|token| token.offset >= span.offset + span.length)
+                    .map_or(usize::MAX, end);
+                start..stop
+            })
+            .collect();
+
+        let mut parser = Parser::new(tokens.clone());
+        if let Err(Errors(parse_errors)) = parser.parse() {
+            errors.extend(parse_errors.into_iter().filter(|error| {
+                error
+                    .span
+                    .is_none_or(|span| !holes.iter().any(|hole| hole.contains(&span.offset)))
+            }));
+        }
+
+        // Outer nodes go before the ones they contain. Nodes are recorded
+        // as they finish, so of two with the same range the later is outside.
+        let mut nodes: Vec<(usize, (NodeKind, Range<usize>))> =
+            parser.take_nodes().into_iter().enumerate().collect();
+        nodes.sort_by_key(|(finished, (_, range))| {
+            (range.start, Reverse(range.end), Reverse(*finished))
+        });
+        let nodes = nodes.into_iter().map(|(_, node)| node).collect();
+
+        let mut builder = Builder {
+            source,
+            tokens,
+            nodes,
+            next_node: 0,
+            position: 0,
+            written: 0,
+        };
+        let count = builder.tokens.len();
+        let root = builder.node(NodeKind::Program, count);
+
+        SyntaxTree { root, errors }
+    }
+
+    pub fn text(&self) -> String {
+        self.root.text()
+    }
+
+    /// Turns the tree into the statements the interpreter runs. A tree
+    /// with errors in it has no such statements; its errors are returned.
+    pub fn lower(&self) -> Result<Vec<Stmt>, Errors> {
+        if !self.errors.is_empty() {
+            return Err(Errors(self.errors.clone()));
+        }
+
+        Parser::new(self.root.tokens()).parse()
+    }
+}
+
+struct Builder<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    nodes: Vec<(NodeKind, Range<usize>)>,
+    next_node: usize,
+    // The next token to place, and the end of the source placed so far.
+    position: usize,
+    written: usize,
+}
+
+impl Builder<'_> {
+    // Builds a node holding the tokens before `end`. Trivia before a token
+    // goes in the outermost node that reaches it, so a node starts and ends
+    // with one of its own tokens.
+    fn node(&mut self, kind: NodeKind, end: usize) -> SyntaxNode {
+        let mut children = Vec::new();
+
+        while self.position < end {
+            let offset = self.tokens[self.position].offset;
+            if self.written < offset {
+                children.extend(trivia(&self.source[self.written..offset], self.written));
+                self.written = offset;
+            }
+
+            // Ranges from a statement the parser gave up on can overlap
+            // the error that replaced it; those are dropped.
+            while let Some((_, range)) = self.nodes.get(self.next_node) {
+                if range.start < self.position || (range.start == self.position && range.end > end)
+                {
+                    self.next_node += 1;
+                } else {
+                    break;
+                }
+            }
+
+            match self.nodes.get(self.next_node) {
+                Some((child, range)) if range.start == self.position => {
+                    let (child, child_end) = (*child, range.end);
+                    self.next_node += 1;
+                    children.push(SyntaxElement::Node(self.node(child, child_end)));
+                }
+                _ => {
+                    let token = self.tokens[self.position].clone();
+                    self.written = offset + token.lexeme.len();
+                    self.position += 1;
+                    children.push(SyntaxElement::Token(token));
+                }
+            }
+        }
+
+        SyntaxNode::new(kind, children)
+    }
+}
+
+// Splits the text between two tokens into whitespace, comments and any
+// characters the lexer rejected.
+fn trivia(text: &str, offset: usize) -> Vec<SyntaxElement> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+
+    while start < text.len() {
+        let rest = &text[start..];
+        let (kind, length) = if rest.starts_with('\n') {
+            (TriviaKind::Newline, 1)
+        } else if rest.starts_with([' ', '\t', '\r']) {
+            let length = rest.len() - rest.trim_start_matches([' ', '\t', '\r']).len();
+            (TriviaKind::Whitespace, length)
+        } else if rest.starts_with("//") {
+            let length = rest.find('\n').unwrap_or(rest.len());
+            match rest.starts_with("///") && !rest.starts_with("////") {
+                true => (TriviaKind::DocComment, length),
+                false => (TriviaKind::LineComment, length),
+            }
+        } else if rest.starts_with("/*") {
+            match block_comment_length(rest) {
+                Some(length) => (TriviaKind::BlockComment, length),
+                None => (TriviaKind::Skipped, rest.len()),
+            }
+        } else {
+            let length = rest.find([' ', '\t', '\r', '\n']).unwrap_or(rest.len());
+            (TriviaKind::Skipped, length)
+        };
+
+        let piece = Trivia {
+            kind,
+            text: rest[..length].to_string(),
+            offset: offset + start,
+        };
+        pieces.push(match kind {
+            TriviaKind::Skipped => SyntaxElement::Node(SyntaxNode::new(
+                NodeKind::Error,
+                vec![SyntaxElement::Trivia(piece)],
+            )),
+            _ => SyntaxElement::Trivia(piece),
+        });
+        start += length;
+    }
+
+    pieces
+}
+
+fn block_comment_length(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut index = 0;
+
+    while index < text.len() {
+        if text[index..].starts_with("/*") {
+            depth += 1;
+            index += 2;
+        } else if text[index..].starts_with("*/") {
+            depth -= 1;
+            index += 2;
+            if depth == 0 {
+                return Some(index);
+            }
+        } else {
+            index += text[index..].chars().next().unwrap().len_utf8();
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dialect::SOMALI;
+
+    #[test]
+    fn test_round_trips_source() {
+        let sources = [
+            "",
+            "weel x = 1;",
+            "// lahjad: so\n/// Laba jeer.\nhawl labo(n) {\n\tceli n * 2; /* /* nested */ */\n}\r\n\n",
+            "weel [a, ...b] = [1, 2, 3];\ndaabac a ?? b[0]   ;  // dhammaad",
+            "weel s = \u{201C}soo dhowoow\u{201D} + \"\"\"\n  raw\n  \"\"\";",
+            "weel x = 1 @ 2;\nhawl (",
+            "weel s = \"furan",
+            "/* aan la xirin",
+        ];
+
+        for source in sources {
+            let tree = SyntaxTree::parse(source, &SOMALI);
+            assert_eq!(tree.text(), source);
+            assert_eq!(tree.root.range, 0..source.len());
+        }
+    }
+
+    #[test]
+    fn test_tree_shape() {
+        let source = "weel x = 1 + 2 * 3; // faallo\nf(x);";
+        let tree = SyntaxTree::parse(source, &SOMALI);
+
+        assert_eq!(
+            tree.root.to_string(),
+            r#"Program@0..35
+  VarDecl@0..19
+    Var@0..4 "weel"
+    Whitespace@4..5 " "
+    Pattern@5..6
+      Identifier@5..6 "x"
+    Whitespace@6..7 " "
+    Equal@7..8 "="
+    Whitespace@8..9 " "
+    Binary@9..18
+      Literal@9..10
+        Number@9..10 "1"
+      Whitespace@10..11 " "
+      Plus@11..12 "+"
+      Whitespace@12..13 " "
+      Binary@13..18
+        Literal@13..14
+          Number@13..14 "2"
+        Whitespace@14..15 " "
+        Star@15..16 "*"
+        Whitespace@16..17 " "
+        Literal@17..18
+          Number@17..18 "3"
+    Semicolon@18..19 ";"
+  Whitespace@19..20 " "
+  LineComment@20..29 "// faallo"
+  Newline@29..30 "\n"
+  ExpressionStmt@30..35
+    Call@30..34
+      Name@30..31
+        Identifier@30..31 "f"
+      LeftParen@31..32 "("
+      Name@32..33
+        Identifier@32..33 "x"
+      RightParen@33..34 ")"
+    Semicolon@34..35 ";"
+  Eof@35..35 ""
+"#
+        );
+    }
+
+    #[test]
+    fn test_errors_become_nodes() {
+        let tree = SyntaxTree::parse("weel x = 1 @;\nweel = 2;\ndaabac x;", &SOMALI);
+
+        assert_eq!(tree.errors.len(), 2);
+        let kinds: Vec<NodeKind> = tree
+            .root
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node.kind),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![NodeKind::VarDecl, NodeKind::Error, NodeKind::PrintStmt]
+        );
+        assert!(tree.lower().is_err());
+    }
+
+    #[test]
+    fn test_lex_errors_do_not_cascade() {
+        let tree = SyntaxTree::parse("weel s = 1 @ 2;\nweel t = 3 $;\nweel = 4;", &SOMALI);
+
+        let messages: Vec<String> = tree.errors.iter().map(|error| error.to_string()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "Unexpected character: @",
+                "Unexpected character: $",
+                "Expected variable name or pattern",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lowering_matches_direct_parse() {
+        let source =
+            "/// Doc.\nhawl f(a, b = 2) { celi a - b - 1; }\nweel r = f(5) * 2;\ndaabac r;";
+        let tree = SyntaxTree::parse(source, &SOMALI);
+
+        let tokens = Lexer::new(source).lex().unwrap();
+        let direct = Parser::new(tokens).parse().unwrap();
+
+        assert_eq!(tree.lower().unwrap(), direct);
+    }
+}
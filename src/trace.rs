@@ -0,0 +1,131 @@
+use std::{
+    io::Write,
+    sync::{
+        atomic::{AtomicU8, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+/// The stages of running a program that can be traced.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Lex,
+    Parse,
+    Resolve,
+    Run,
+}
+
+impl Phase {
+    const ALL: [Phase; 4] = [Phase::Lex, Phase::Parse, Phase::Resolve, Phase::Run];
+
+    fn name(self) -> &'static str {
+        match self {
+            Phase::Lex => "lex",
+            Phase::Parse => "parse",
+            Phase::Resolve => "resolve",
+            Phase::Run => "run",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+
+    /// Reads a comma-separated list such as `parse,run`, or `all`.
+    pub fn parse_list(list: &str) -> Option<Vec<Phase>> {
+        let mut phases = Vec::new();
+        for name in list.split(',').map(str::trim) {
+            match name {
+                "all" => phases.extend(Phase::ALL),
+                _ => phases.push(*Phase::ALL.iter().find(|phase| phase.name() == name)?),
+            }
+        }
+        Some(phases)
+    }
+}
+
+// Tracing is switched on once at startup and read from every phase, so it
+// lives in process-wide state rather than being threaded through them.
+static PHASES: AtomicU8 = AtomicU8::new(0);
+static DEPTH: AtomicUsize = AtomicUsize::new(0);
+static OUTPUT: Mutex<Option<Box<dyn Write + Send>>> = Mutex::new(None);
+
+pub fn enable(phases: &[Phase], output: Box<dyn Write + Send>) {
+    *OUTPUT.lock().unwrap() = Some(output);
+    let bits = phases.iter().fold(0, |bits, phase| bits | phase.bit());
+    PHASES.store(bits, Ordering::Relaxed);
+}
+
+pub fn enabled(phase: Phase) -> bool {
+    PHASES.load(Ordering::Relaxed) & phase.bit() != 0
+}
+
+/// Writes one trace line. The message is only built when `phase` is on.
+pub fn event(phase: Phase, message: impl FnOnce() -> String) {
+    event_at(phase, 0, message);
+}
+
+/// Like `event`, indented `depth` levels deeper than the current section.
+pub fn event_at(phase: Phase, depth: usize, message: impl FnOnce() -> String) {
+    if !enabled(phase) {
+        return;
+    }
+
+    let depth = DEPTH.load(Ordering::Relaxed) + depth;
+    if let Some(output) = OUTPUT.lock().unwrap().as_mut() {
+        let _ = output.write_all(line(phase, depth, &message()).as_bytes());
+    }
+}
+
+/// Writes a trace line and indents everything traced until the returned
+/// guard is dropped.
+pub fn section(phase: Phase, message: impl FnOnce() -> String) -> Section {
+    if !enabled(phase) {
+        return Section { active: false };
+    }
+
+    event(phase, message);
+    DEPTH.fetch_add(1, Ordering::Relaxed);
+    Section { active: true }
+}
+
+pub struct Section {
+    active: bool,
+}
+
+impl Drop for Section {
+    fn drop(&mut self) {
+        if self.active {
+            DEPTH.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+fn line(phase: Phase, depth: usize, message: &str) -> String {
+    format!(
+        "{:<10}{}{}\n",
+        format!("[{}]", phase.name()),
+        "  ".repeat(depth),
+        message
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_phase_lists_and_lines() {
+        assert_eq!(
+            Phase::parse_list("parse, run"),
+            Some(vec![Phase::Parse, Phase::Run])
+        );
+        assert_eq!(Phase::parse_list("all").unwrap().len(), 4);
+        assert_eq!(Phase::parse_list("parse,typo"), None);
+
+        assert_eq!(
+            line(Phase::Resolve, 2, "declare x"),
+            "[resolve]     declare x\n"
+        );
+    }
+}